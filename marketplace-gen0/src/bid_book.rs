@@ -0,0 +1,241 @@
+//! Per-listing bid storage.
+//!
+//! Every bid lives in its own `bids` dictionary entry keyed by listing and bidder, so looking up a
+//! single bid never touches the rest of the book. The bidders of a listing are also kept as a list
+//! with one `bid_list` entry per position, its length in `bid_list_length` and the position of
+//! every bidder in `bid_positions`; a removed bidder is replaced by the last one of the list, as in
+//! the listing indexes. `bid_book` only holds the `TOP_OF_BOOK_SIZE` highest bids sorted by
+//! ascending price, so its last entry is the top of the book.
+//!
+//! Placing, raising or revoking a bid therefore writes a bounded number of entries whatever the
+//! size of the book. Two cases read the whole book, without writing more: removing one of the top
+//! bids looks for the bid that moves up, and a bid on a full book looks for the lowest bid to
+//! evict. `max_bids_per_token` bounds those reads.
+use alloc::{string::String, vec::Vec};
+
+use casper_contract::{contract_api::runtime, unwrap_or_revert::UnwrapOrRevert};
+use casper_types::{bytesrepr::ToBytes, Key, U256};
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{get_dictionary_value_from_key, get_key, write_dictionary_value_from_key};
+
+/// A bidder and the price of their bid.
+pub(crate) type Bid = (Key, U256);
+
+fn bid_key(market_key: &str, bidder: Key) -> String {
+    let mut preimage = market_key.as_bytes().to_vec();
+    preimage.extend(bidder.to_bytes().unwrap_or_revert());
+    hex::encode(runtime::blake2b(preimage))
+}
+
+fn list_entry_key(market_key: &str, position: u32) -> String {
+    let mut preimage = market_key.as_bytes().to_vec();
+    preimage.extend(position.to_bytes().unwrap_or_revert());
+    hex::encode(runtime::blake2b(preimage))
+}
+
+fn bid_count(market_key: &str) -> u32 {
+    get_dictionary_value_from_key::<u32>(BID_LIST_LENGTH, market_key).unwrap_or_default()
+}
+
+fn set_bid_count(market_key: &str, count: u32) {
+    write_dictionary_value_from_key(BID_LIST_LENGTH, market_key, count);
+}
+
+fn bidder_at(market_key: &str, position: u32) -> Key {
+    get_dictionary_value_from_key::<Key>(BID_LIST, &list_entry_key(market_key, position))
+        .unwrap_or_revert()
+}
+
+fn position_of(market_key: &str, bidder: Key) -> Option<u32> {
+    get_dictionary_value_from_key::<Option<u32>>(BID_POSITIONS, &bid_key(market_key, bidder))
+        .flatten()
+}
+
+fn set_position(market_key: &str, bidder: Key, position: Option<u32>) {
+    write_dictionary_value_from_key(BID_POSITIONS, &bid_key(market_key, bidder), position);
+}
+
+fn set_price(market_key: &str, bidder: Key, bidding_price: U256) {
+    write_dictionary_value_from_key(BIDS, &bid_key(market_key, bidder), bidding_price);
+}
+
+fn read_top(market_key: &str) -> Vec<Bid> {
+    get_dictionary_value_from_key::<Vec<Bid>>(BID_BOOK, market_key).unwrap_or_default()
+}
+
+fn insert_sorted(index: &mut Vec<Bid>, bidder: Key, bidding_price: U256) {
+    let idx = index.partition_point(|&(_, price)| price < bidding_price);
+    index.insert(idx, (bidder, bidding_price));
+}
+
+pub(crate) fn max_bids_per_token() -> u32 {
+    get_key::<u32>(MAX_BIDS_PER_TOKEN).unwrap_or(DEFAULT_MAX_BIDS_PER_TOKEN)
+}
+
+/// Returns the current bid of `bidder` on the listing, if any.
+pub(crate) fn bid_of(market_key: &str, bidder: Key) -> Option<U256> {
+    get_dictionary_value_from_key::<U256>(BIDS, &bid_key(market_key, bidder))
+        .filter(|price| !price.is_zero())
+}
+
+/// Returns the highest bid on the listing.
+pub(crate) fn top_bid(market_key: &str) -> Option<Bid> {
+    read_top(market_key).last().copied()
+}
+
+/// Returns the highest bid on the listing placed by anyone other than `bidder`.
+pub(crate) fn highest_bid_excluding(market_key: &str, bidder: Key) -> Option<U256> {
+    // the top of the book holds at least two bids whenever the listing has two
+    read_top(market_key)
+        .iter()
+        .rev()
+        .find(|(key, _)| *key != bidder)
+        .map(|(_, price)| *price)
+}
+
+/// Returns every bid on the listing, in no particular order.
+pub(crate) fn bids(market_key: &str) -> Vec<Bid> {
+    (0..bid_count(market_key))
+        .map(|position| {
+            let bidder = bidder_at(market_key, position);
+            (bidder, bid_of(market_key, bidder).unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Records a new bid.
+///
+/// When the book is already full the bid has to beat the lowest one, which is evicted and
+/// returned so that the caller can refund it.
+pub(crate) fn add_bid(market_key: &str, bidder: Key, bidding_price: U256) -> Option<Bid> {
+    match make_room(market_key, bidding_price) {
+        Ok(evicted) => {
+            insert(market_key, bidder, bidding_price);
+            evicted
+        }
+        Err(()) => runtime::revert(Error::BidBookFull),
+    }
+}
+
+/// Adds `bidding_price` to the bid of `bidder`, placing a new bid if there is none, for bids
+/// carried over from a legacy record or another marketplace.
+///
+/// Returns the bid left out of the book for the caller to refund: the lowest one when the book
+/// was full and the new bid beat it, or the new bid itself when it did not.
+pub(crate) fn merge_bid(market_key: &str, bidder: Key, bidding_price: U256) -> Option<Bid> {
+    if let Some(current_price) = bid_of(market_key, bidder) {
+        raise_bid(market_key, bidder, current_price + bidding_price);
+        return None;
+    }
+    match make_room(market_key, bidding_price) {
+        Ok(evicted) => {
+            insert(market_key, bidder, bidding_price);
+            evicted
+        }
+        Err(()) => Some((bidder, bidding_price)),
+    }
+}
+
+/// Raises the existing bid of `bidder` to `bidding_price`.
+pub(crate) fn raise_bid(market_key: &str, bidder: Key, bidding_price: U256) {
+    set_price(market_key, bidder, bidding_price);
+
+    let mut top = read_top(market_key);
+    if top.iter().any(|&(key, _)| key == bidder) {
+        top.retain(|&(key, _)| key != bidder);
+    } else {
+        match top.first() {
+            Some(&(_, lowest_top_price)) if bidding_price > lowest_top_price => {
+                top.remove(0);
+            }
+            _ => return,
+        }
+    }
+    insert_sorted(&mut top, bidder, bidding_price);
+    write_dictionary_value_from_key(BID_BOOK, market_key, top);
+}
+
+/// Removes the bid of `bidder` and returns its price.
+pub(crate) fn remove_bid(market_key: &str, bidder: Key) -> Option<U256> {
+    let bidding_price = bid_of(market_key, bidder)?;
+    set_price(market_key, bidder, U256::zero());
+
+    if let Some(position) = position_of(market_key, bidder) {
+        let last = bid_count(market_key).checked_sub(1).unwrap_or_revert();
+        set_bid_count(market_key, last);
+        if position != last {
+            let moved = bidder_at(market_key, last);
+            write_dictionary_value_from_key(BID_LIST, &list_entry_key(market_key, position), moved);
+            set_position(market_key, moved, Some(position));
+        }
+        set_position(market_key, bidder, None);
+    }
+
+    let mut top = read_top(market_key);
+    if top.iter().any(|&(key, _)| key == bidder) {
+        top.retain(|&(key, _)| key != bidder);
+        // the highest bid left outside the top of the book moves up
+        let next = bids(market_key)
+            .into_iter()
+            .filter(|&(key, _)| top.iter().all(|&(top_bidder, _)| top_bidder != key))
+            .max_by(|(_, a), (_, b)| a.cmp(b));
+        if let Some((next_bidder, next_price)) = next {
+            insert_sorted(&mut top, next_bidder, next_price);
+        }
+        write_dictionary_value_from_key(BID_BOOK, market_key, top);
+    }
+    Some(bidding_price)
+}
+
+/// Removes every bid on the listing and returns them.
+pub(crate) fn clear_bids(market_key: &str) -> Vec<Bid> {
+    let bids = bids(market_key);
+    for &(bidder, _) in &bids {
+        set_price(market_key, bidder, U256::zero());
+        set_position(market_key, bidder, None);
+    }
+    set_bid_count(market_key, 0);
+    write_dictionary_value_from_key(BID_BOOK, market_key, Vec::<Bid>::new());
+    bids
+}
+
+// Appends a bidder without a bid to the list and, when it ranks there, to the top of the book.
+fn insert(market_key: &str, bidder: Key, bidding_price: U256) {
+    let position = bid_count(market_key);
+    write_dictionary_value_from_key(BID_LIST, &list_entry_key(market_key, position), bidder);
+    set_bid_count(market_key, position + 1);
+    set_position(market_key, bidder, Some(position));
+    set_price(market_key, bidder, bidding_price);
+
+    let mut top = read_top(market_key);
+    if top.len() >= TOP_OF_BOOK_SIZE {
+        match top.first() {
+            Some(&(_, lowest_top_price)) if bidding_price > lowest_top_price => {
+                top.remove(0);
+            }
+            _ => return,
+        }
+    }
+    insert_sorted(&mut top, bidder, bidding_price);
+    write_dictionary_value_from_key(BID_BOOK, market_key, top);
+}
+
+// Evicts the lowest bid when the book is full and `bidding_price` beats it, and fails when it
+// does not.
+fn make_room(market_key: &str, bidding_price: U256) -> Result<Option<Bid>, ()> {
+    if bid_count(market_key) < max_bids_per_token() {
+        return Ok(None);
+    }
+    let lowest_bid = bids(market_key)
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.cmp(b));
+    match lowest_bid {
+        Some((lowest_bidder, lowest_price)) if bidding_price > lowest_price => {
+            remove_bid(market_key, lowest_bidder);
+            Ok(Some((lowest_bidder, lowest_price)))
+        }
+        _ => Err(()),
+    }
+}
@@ -35,6 +35,7 @@ pub const REQUEST_BRIDGE_ENTRY_POINT_NAME: &str = "request_bridge_nft";
 pub const UNLOCK_NFT_ENTRY_POINT_NAME: &str = "unlock_nft";
 pub const TRANSFER_OWNER_ENTRY_POINT_NAME: &str = "transfer_owner";
 pub const INIT_ENTRY_POINT_NAME: &str = "init";
+pub const MIGRATE_ENTRY_POINT_NAME: &str = "migrate";
 
 // Runtime argument names
 pub const ARG_CONTRACT_HASH: &str = "contract_hash";
@@ -47,6 +48,7 @@ pub const ARG_NFT_CONTRACT_HASH: &str = "nft_contract_hash";
 pub const ARG_IDENTIFIER_MODE: &str = "identifier_mode";
 pub const ARG_UNLOCK_ID: &str = "unlock_id";
 pub const ARG_CONTRACT_OWNER: &str = "contract_owner";
+pub const ARG_CONTRACT_VERSION: &str = "contract_version";
 pub const ARG_NFT_ENABLED: &str = "nft_enabled";
 pub const ARG_MINIMUM_OFFER: &str = "minimum_offer";
 pub const ARG_ACCEPTED_PRICE: &str = "accepted_price";
@@ -149,8 +151,14 @@ pub const TOKEN_IDS: &str = "token_ids";
 pub const NFT_SUPPPORTED_CONTRACTS: &str = "nft_supported_contracts";
 pub const TOKEN_MARKET: &str = "token_market";
 pub const SELLING_IN_MARKET: &str = "selling_in_market";
+/// Version byte written at the start of every `selling_in_market` record.
+pub const SELLING_IN_MARKET_VERSION: u8 = 1;
 pub const TOKEN_MARKET_LIST: &str = "token_market_list";
 pub const SELLING_LIST: &str = "selling_list";
+pub const TOKEN_MARKET_LIST_LENGTH: &str = "token_market_list_length";
+pub const SELLING_LIST_LENGTH: &str = "selling_list_length";
+pub const LISTING_INDEX_POSITIONS: &str = "listing_index_positions";
+/// Market fee in thousandths kept by versions before `MARKET_FEE_BPS`.
 pub const MARKET_FEE: &str = "market_fee";
 pub const OPERATOR: &str = "operator";
 pub const NUMBER_OF_MINTED_TOKENS: &str = "number_of_minted_tokens";
@@ -239,3 +247,174 @@ pub const IS_ROYALTY: &str = "is_royalty";
 pub const TOKEN_MARKET_INDEX: &str = "token_market_index";
 pub const ARG_BUYER: &str = "buyer";
 pub const ARG_BIDDER: &str = "bidder";
+pub const ARG_MIN_BID: &str = "min_bid";
+pub const ARG_MIN_BID_INCREMENT: &str = "min_bid_increment";
+pub const ARG_MIN_BID_INCREMENT_BPS: &str = "min_bid_increment_bps";
+pub const MIN_BID: &str = "min_bid";
+pub const MIN_BID_INCREMENT: &str = "min_bid_increment";
+pub const MIN_BID_INCREMENT_BPS: &str = "min_bid_increment_bps";
+pub const SET_BID_CONFIG_ENTRY_POINT_NAME: &str = "set_bid_config";
+pub const BIDS: &str = "bids";
+pub const BID_BOOK: &str = "bid_book";
+pub const MAX_BIDS_PER_TOKEN: &str = "max_bids_per_token";
+pub const ARG_MAX_BIDS_PER_TOKEN: &str = "max_bids_per_token";
+pub const SET_MAX_BIDS_PER_TOKEN_ENTRY_POINT_NAME: &str = "set_max_bids_per_token";
+/// Size of a token's bid book when `max_bids_per_token` has not been set.
+pub const DEFAULT_MAX_BIDS_PER_TOKEN: u32 = 16;
+/// Highest `max_bids_per_token` allowed, which bounds the bids read when a book is full or loses
+/// one of its top bids.
+pub const MAX_BIDS_PER_TOKEN_LIMIT: u32 = 64;
+/// Minimum bid (100 CSPR) used when a collection has no `min_bid` configured.
+pub const DEFAULT_MIN_BID: u64 = 100_000_000_000;
+/// Denominator for values expressed in basis points.
+pub const BPS_DIVISOR: u64 = 10_000;
+pub const ARG_NFT_STANDARD: &str = "nft_standard";
+/// NFT standards a supported collection can follow, stored per collection in
+/// `nft_supported_contracts`. Zero marks a collection that is no longer supported.
+pub const NFT_STANDARD_CEP47: u8 = 1;
+pub const NFT_STANDARD_CEP78: u8 = 2;
+/// Standard of the collection the contract was installed for.
+pub const NATIVE_NFT_STANDARD: u8 = NFT_STANDARD_CEP47;
+pub const ARG_TARGET_MARKETPLACE: &str = "target_marketplace";
+pub const ARG_SOURCE_MARKETPLACE: &str = "source_marketplace";
+pub const ARG_IS_ACTIVE: &str = "is_active";
+pub const ARG_BIDS: &str = "bids";
+pub const ARG_ENABLED: &str = "enabled";
+pub const MIGRATION_TARGET: &str = "migration_target";
+pub const MIGRATION_SOURCES: &str = "migration_sources";
+pub const MIGRATED_LISTINGS: &str = "migrated_listings";
+pub const MIGRATE_LISTINGS_ENTRY_POINT_NAME: &str = "migrate_listings";
+pub const IMPORT_LISTING_ENTRY_POINT_NAME: &str = "import_listing";
+pub const SET_MIGRATION_SOURCE_ENTRY_POINT_NAME: &str = "set_migration_source";
+pub const PAUSED: &str = "paused";
+pub const COLLECTION_PAUSED: &str = "collection_paused";
+pub const ARG_PAUSED: &str = "paused";
+pub const SET_PAUSED_ENTRY_POINT_NAME: &str = "set_paused";
+pub const SET_COLLECTION_PAUSED_ENTRY_POINT_NAME: &str = "set_collection_paused";
+/// Operation classes that can be paused, combined as a bitmask in `paused` and
+/// `collection_paused`. Revoking a listing or a bid is never paused.
+pub const PAUSE_LISTING: u8 = 1;
+pub const PAUSE_BUYING: u8 = 2;
+pub const PAUSE_BIDDING: u8 = 4;
+pub const PAUSE_ALL: u8 = PAUSE_LISTING | PAUSE_BUYING | PAUSE_BIDDING;
+pub const ROLES: &str = "roles";
+pub const ARG_ACCOUNT: &str = "account";
+pub const ARG_ROLE: &str = "role";
+pub const GRANT_ROLE_ENTRY_POINT_NAME: &str = "grant_role";
+pub const REVOKE_ROLE_ENTRY_POINT_NAME: &str = "revoke_role";
+pub const SET_FEE_RECEIVER_ENTRY_POINT_NAME: &str = "set_fee_receiver";
+/// Roles an account can be granted, combined as a bitmask in `roles`.
+pub const ROLE_ADMIN: u8 = 1;
+pub const ROLE_FEE_MANAGER: u8 = 2;
+pub const ROLE_CURATOR: u8 = 4;
+pub const ROLE_PAUSER: u8 = 8;
+pub const ROLE_ALL: u8 = ROLE_ADMIN | ROLE_FEE_MANAGER | ROLE_CURATOR | ROLE_PAUSER;
+pub const PENDING_CONTRACT_OWNER: &str = "pending_contract_owner";
+pub const PROPOSE_OWNER_ENTRY_POINT_NAME: &str = "propose_owner";
+pub const ACCEPT_OWNERSHIP_ENTRY_POINT_NAME: &str = "accept_ownership";
+pub const CANCEL_OWNERSHIP_TRANSFER_ENTRY_POINT_NAME: &str = "cancel_ownership_transfer";
+pub const PENDING_MARKET_FEE: &str = "pending_market_fee";
+pub const PENDING_MARKET_FEE_RECEIVER: &str = "pending_market_fee_receiver";
+pub const ARG_EFFECTIVE_AT: &str = "effective_at";
+pub const ARG_CONFIG: &str = "config";
+pub const EXECUTE_CONFIG_CHANGE_ENTRY_POINT_NAME: &str = "execute_config_change";
+pub const CANCEL_CONFIG_CHANGE_ENTRY_POINT_NAME: &str = "cancel_config_change";
+/// Settings changed through the queue, passed as `config` to execute or cancel a change.
+pub const CONFIG_MARKET_FEE: u8 = 1;
+pub const CONFIG_MARKET_FEE_RECEIVER: u8 = 2;
+/// Minimum time in milliseconds (48 hours) between scheduling a configuration change and
+/// applying it.
+pub const CONFIG_CHANGE_DELAY: u64 = 48 * 60 * 60 * 1000;
+pub const EMERGENCY_SHUTDOWN: &str = "emergency_shutdown";
+pub const EMERGENCY_SHUTDOWN_ENTRY_POINT_NAME: &str = "emergency_shutdown";
+pub const EVENTS: &str = "__events";
+pub const EVENTS_LENGTH: &str = "__events_length";
+pub const EVENTS_SCHEMA: &str = "__events_schema";
+pub const EVENTS_CES_VERSION: &str = "__events_ces_version";
+pub const CES_VERSION: &str = "0.1.0";
+pub const EVENT_PREFIX: &str = "event_";
+/// How a sale was settled, carried by `DoneSell` as `route`.
+pub const TRADE_ROUTE_BUY: u8 = 1;
+pub const TRADE_ROUTE_BID: u8 = 2;
+pub const TRADE_ROUTE_ACCEPTED_BID: u8 = 3;
+pub const TRADE_ROUTE_PRICE_CROSSING: u8 = 4;
+/// Why CSPR held by the market was sent back, carried by `Refund` as `reason`.
+pub const REFUND_BID_REVOKED: u8 = 1;
+pub const REFUND_BID_EVICTED: u8 = 2;
+pub const REFUND_BID_ON_BUY: u8 = 3;
+pub const REFUND_PRICE_DIFFERENCE: u8 = 4;
+pub const ARG_COUNT: &str = "count";
+pub const GET_LISTING_ENTRY_POINT_NAME: &str = "get_listing";
+pub const GET_BID_ENTRY_POINT_NAME: &str = "get_bid";
+pub const GET_TOP_BIDS_ENTRY_POINT_NAME: &str = "get_top_bids";
+pub const GET_MARKET_CONFIG_ENTRY_POINT_NAME: &str = "get_market_config";
+pub const GET_COLLECTION_CONFIG_ENTRY_POINT_NAME: &str = "get_collection_config";
+pub const ARG_OFFSET: &str = "offset";
+pub const ARG_LIMIT: &str = "limit";
+pub const GET_SELLER_LISTINGS_ENTRY_POINT_NAME: &str = "get_seller_listings";
+pub const GET_COLLECTION_LISTINGS_ENTRY_POINT_NAME: &str = "get_collection_listings";
+pub const INDEX_LISTINGS_ENTRY_POINT_NAME: &str = "index_listings";
+pub const COLLECTION_STATS: &str = "collection_stats";
+pub const COLLECTION_FLOOR: &str = "collection_floor";
+pub const LAST_SALE: &str = "last_sale";
+pub const GET_COLLECTION_STATS_ENTRY_POINT_NAME: &str = "get_collection_stats";
+pub const GET_LAST_SALE_ENTRY_POINT_NAME: &str = "get_last_sale";
+pub const TWAP_OBSERVATIONS: &str = "twap_observations";
+pub const TWAP_RING: &str = "twap_ring";
+/// Sales kept per collection for the TWAP, which bounds how far back a window can reach.
+pub const TWAP_OBSERVATION_SLOTS: u32 = 256;
+/// Most a single sale moves the price the TWAP records, in basis points of the previous one.
+pub const TWAP_MAX_PRICE_STEP_BPS: u64 = 1_000;
+pub const ARG_WINDOW: &str = "window";
+pub const ARG_MIN_SALES: &str = "min_sales";
+pub const GET_TWAP_ENTRY_POINT_NAME: &str = "get_twap";
+pub const ARG_MAX_PRICE: &str = "max_price";
+pub const ARG_DEADLINE: &str = "deadline";
+pub const REFERRAL_SHARE_BPS: &str = "referral_share_bps";
+pub const REFERRAL_TOTALS: &str = "referral_totals";
+pub const ARG_REFERRER: &str = "referrer";
+pub const ARG_REFERRAL_SHARE_BPS: &str = "referral_share_bps";
+pub const SET_REFERRAL_SHARE_ENTRY_POINT_NAME: &str = "set_referral_share";
+pub const GET_REFERRAL_TOTAL_ENTRY_POINT_NAME: &str = "get_referral_total";
+pub const FEE_RECIPIENTS: &str = "fee_recipients";
+pub const PENDING_FEE_RECIPIENTS: &str = "pending_fee_recipients";
+pub const ARG_FEE_RECIPIENTS: &str = "fee_recipients";
+pub const SET_FEE_RECIPIENTS_ENTRY_POINT_NAME: &str = "set_fee_recipients";
+pub const CONFIG_FEE_RECIPIENTS: u8 = 3;
+/// Upper bound on the recipients the market fee is split between, each one is a transfer per sale.
+pub const MAX_FEE_RECIPIENTS: usize = 10;
+pub const MARKET_FEE_BPS: &str = "market_fee_bps";
+/// Highest market fee, in basis points, the fee manager can set (20%).
+pub const MAX_MARKET_FEE_BPS: u64 = 2_000;
+pub const ROUTE_FEES: &str = "route_fees";
+pub const COLLECTION_FEES: &str = "collection_fees";
+pub const ARG_ROUTE: &str = "route";
+pub const ARG_FEE_BPS: &str = "fee_bps";
+pub const SET_FEE_OVERRIDE_ENTRY_POINT_NAME: &str = "set_fee_override";
+pub const GET_EFFECTIVE_FEE_ENTRY_POINT_NAME: &str = "get_effective_fee";
+/// Route of a collection fee override that applies to every trade route of the collection.
+pub const FEE_ROUTE_ANY: u8 = 0;
+pub const APPROVED_MIGRATION_TARGET: &str = "approved_migration_target";
+pub const PENDING_MIGRATION_TARGET: &str = "pending_migration_target";
+pub const SET_MIGRATION_TARGET_ENTRY_POINT_NAME: &str = "set_migration_target";
+pub const CLEAR_MIGRATION_TARGET_ENTRY_POINT_NAME: &str = "clear_migration_target";
+pub const CONFIG_MIGRATION_TARGET: u8 = 4;
+/// Fee override changes waiting for their effective time, one per collection and trade route.
+pub const PENDING_FEE_OVERRIDES: &str = "pending_fee_overrides";
+pub const CONFIG_FEE_OVERRIDE: u8 = 5;
+pub const FLOOR_SCANS: &str = "floor_scans";
+/// Listings a trade or listing change reads when it has to search for a collection's floor.
+pub const FLOOR_SCAN_LIMIT: u32 = 32;
+pub const REFRESH_FLOOR_ENTRY_POINT_NAME: &str = "refresh_floor";
+pub const BID_LIST: &str = "bid_list";
+pub const BID_LIST_LENGTH: &str = "bid_list_length";
+pub const BID_POSITIONS: &str = "bid_positions";
+/// Highest bids of a token kept sorted in its `bid_book` entry.
+pub const TOP_OF_BOOK_SIZE: usize = 4;
+/// Number of CEP-78 tokens escrowed under the running contract hash.
+pub const ESCROWED_CEP78_TOKENS: &str = "escrowed_cep78_tokens";
+/// Set by the upgrader of a version from before `escrowed_cep78_tokens`, once it escrows nothing.
+pub const ARG_ESCROW_EMPTY: &str = "escrow_empty";
+pub const ARG_PACKAGE_ACCESS: &str = "package_access";
+/// Address of the package access URef, which authorizes `migrate`.
+pub const PACKAGE_ACCESS_ADDR: &str = "package_access_addr";
@@ -77,6 +77,44 @@ pub enum Error {
     MissingBidderCurrentIndex = 151,
     InvalidBidderCurrentIndex = 152,
     InvalidSort = 153,
+    BidBelowMinimum = 154,
+    BidIncrementTooLow = 155,
+    InvalidBidIncrementBps = 156,
+    BidBookFull = 157,
+    InvalidMaxBidsPerToken = 158,
+    MissingContractPackage = 159,
+    MigrationTargetMismatch = 160,
+    InvalidMigrationSource = 161,
+    ListingAlreadyActive = 162,
+    MarketplaceMigrating = 163,
+    InvalidNFTStandard = 164,
+    MarketPaused = 165,
+    InvalidPauseFlags = 166,
+    MissingRole = 167,
+    InvalidRole = 168,
+    NoPendingOwner = 169,
+    CallerNotPendingOwner = 170,
+    EffectiveTimeTooEarly = 171,
+    NoPendingConfigChange = 172,
+    ConfigChangeNotDue = 173,
+    InvalidConfig = 174,
+    MarketShutdown = 175,
+    MarketNotShutdown = 176,
+    InvalidTwapWindow = 177,
+    TwapWindowTooLong = 178,
+    DeadlinePassed = 179,
+    PriceAboveMax = 180,
+    InvalidReferrer = 181,
+    InvalidReferralShare = 182,
+    InvalidFeeRecipients = 183,
+    InvalidFeeRoute = 184,
+    InvalidMinBid = 185,
+    MissingContractHash = 186,
+    MigrationTargetNotScheduled = 187,
+    CallerNotOwner = 188,
+    TwapTooFewSales = 189,
+    EscrowNotEmpty = 190,
+    InvalidPackageAccess = 191,
 }
 
 impl From<Error> for ApiError {
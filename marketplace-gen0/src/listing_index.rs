@@ -0,0 +1,170 @@
+//! Indexes of active listings per seller and per collection.
+//!
+//! Each index keeps, for every seller or collection, a list of `(nft_contract, token_id)` entries
+//! with one dictionary entry per position and its length in a second dictionary, so listings can
+//! be read a page at a time. A removed entry is replaced by the last one of its list, and
+//! `listing_index_positions` records where every indexed listing sits, so neither adding nor
+//! removing a listing walks a list.
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use casper_contract::{contract_api::runtime, unwrap_or_revert::UnwrapOrRevert};
+use casper_types::{bytesrepr::ToBytes, Key};
+
+use crate::constants::*;
+use crate::helpers::{
+    get_dictionary_value_from_key, get_token_market_key, make_dictionary_item_key_for_key,
+    write_dictionary_value_from_key,
+};
+
+/// A listed token: its collection and token id.
+pub(crate) type ListingRef = (Key, String);
+
+/// A page of an index: the number of listings in the whole list, then the requested entries.
+pub(crate) type ListingPage = (u32, Vec<ListingRef>);
+
+// Seller, position in the seller's list and position in the collection's list.
+type Positions = (Key, u32, u32);
+
+struct Index {
+    entries: &'static str,
+    lengths: &'static str,
+}
+
+const BY_SELLER: Index = Index {
+    entries: SELLING_LIST,
+    lengths: SELLING_LIST_LENGTH,
+};
+
+const BY_COLLECTION: Index = Index {
+    entries: TOKEN_MARKET_LIST,
+    lengths: TOKEN_MARKET_LIST_LENGTH,
+};
+
+impl Index {
+    fn entry_key(owner: Key, position: u32) -> String {
+        let mut preimage = owner.to_bytes().unwrap_or_revert();
+        preimage.extend(position.to_bytes().unwrap_or_revert());
+        hex::encode(runtime::blake2b(preimage))
+    }
+
+    fn len(&self, owner: Key) -> u32 {
+        get_dictionary_value_from_key::<u32>(self.lengths, &make_dictionary_item_key_for_key(owner))
+            .unwrap_or_default()
+    }
+
+    fn set_len(&self, owner: Key, len: u32) {
+        write_dictionary_value_from_key(
+            self.lengths,
+            &make_dictionary_item_key_for_key(owner),
+            len,
+        );
+    }
+
+    fn get(&self, owner: Key, position: u32) -> ListingRef {
+        get_dictionary_value_from_key::<ListingRef>(
+            self.entries,
+            &Index::entry_key(owner, position),
+        )
+        .unwrap_or_revert()
+    }
+
+    fn set(&self, owner: Key, position: u32, listing: ListingRef) {
+        write_dictionary_value_from_key(self.entries, &Index::entry_key(owner, position), listing);
+    }
+
+    fn push(&self, owner: Key, listing: ListingRef) -> u32 {
+        let position = self.len(owner);
+        self.set(owner, position, listing);
+        self.set_len(owner, position + 1);
+        position
+    }
+
+    // Removes the entry at `position`, returning the listing moved into its place, if any. The
+    // old last entry stays in the dictionary but falls outside the list.
+    fn swap_remove(&self, owner: Key, position: u32) -> Option<ListingRef> {
+        let last = self.len(owner).checked_sub(1).unwrap_or_revert();
+        self.set_len(owner, last);
+        if position == last {
+            return None;
+        }
+        let moved = self.get(owner, last);
+        self.set(owner, position, moved.clone());
+        Some(moved)
+    }
+
+    fn page(&self, owner: Key, offset: u32, limit: u32) -> ListingPage {
+        let len = self.len(owner);
+        let end = offset.saturating_add(limit).min(len);
+        let listings = (offset..end)
+            .map(|position| self.get(owner, position))
+            .collect();
+        (len, listings)
+    }
+}
+
+fn positions_of(market_key: &str) -> Option<Positions> {
+    get_dictionary_value_from_key::<Option<Positions>>(LISTING_INDEX_POSITIONS, market_key)
+        .flatten()
+}
+
+fn set_positions(market_key: &str, positions: Option<Positions>) {
+    write_dictionary_value_from_key(LISTING_INDEX_POSITIONS, market_key, positions);
+}
+
+/// Adds an active listing to the indexes of its seller and collection, unless already there.
+pub(crate) fn insert(seller: Key, nft_contract: Key, token_id: &str) {
+    let market_key = get_token_market_key(&nft_contract, token_id);
+    if positions_of(&market_key).is_some() {
+        return;
+    }
+    let listing = (nft_contract, token_id.to_string());
+    let seller_position = BY_SELLER.push(seller, listing.clone());
+    let collection_position = BY_COLLECTION.push(nft_contract, listing);
+    set_positions(
+        &market_key,
+        Some((seller, seller_position, collection_position)),
+    );
+}
+
+/// Removes a listing from both indexes. Listings that are not indexed are ignored.
+pub(crate) fn remove(nft_contract: Key, token_id: &str) {
+    let market_key = get_token_market_key(&nft_contract, token_id);
+    let (seller, seller_position, collection_position) = match positions_of(&market_key) {
+        Some(positions) => positions,
+        None => return,
+    };
+
+    if let Some((moved_contract, moved_token_id)) = BY_SELLER.swap_remove(seller, seller_position) {
+        let moved_key = get_token_market_key(&moved_contract, &moved_token_id);
+        let (moved_seller, _, moved_collection_position) =
+            positions_of(&moved_key).unwrap_or_revert();
+        set_positions(
+            &moved_key,
+            Some((moved_seller, seller_position, moved_collection_position)),
+        );
+    }
+    if let Some((moved_contract, moved_token_id)) =
+        BY_COLLECTION.swap_remove(nft_contract, collection_position)
+    {
+        let moved_key = get_token_market_key(&moved_contract, &moved_token_id);
+        let (moved_seller, moved_seller_position, _) = positions_of(&moved_key).unwrap_or_revert();
+        set_positions(
+            &moved_key,
+            Some((moved_seller, moved_seller_position, collection_position)),
+        );
+    }
+    set_positions(&market_key, None);
+}
+
+/// Returns up to `limit` active listings of `seller`, starting at `offset`.
+pub(crate) fn seller_listings(seller: Key, offset: u32, limit: u32) -> ListingPage {
+    BY_SELLER.page(seller, offset, limit)
+}
+
+/// Returns up to `limit` active listings of the collection, starting at `offset`.
+pub(crate) fn collection_listings(nft_contract: Key, offset: u32, limit: u32) -> ListingPage {
+    BY_COLLECTION.page(nft_contract, offset, limit)
+}
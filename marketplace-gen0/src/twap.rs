@@ -0,0 +1,143 @@
+//! Time-weighted average sale price per collection.
+//!
+//! Every sale stores an observation `(timestamp, cumulative, price)`: `price` is the recorded sale
+//! price and `cumulative` the sum of every earlier recorded price multiplied by the milliseconds it
+//! remained the last one. The latest `TWAP_OBSERVATION_SLOTS` observations of a collection are kept
+//! in a ring, and the average over a window is the difference between the cumulative at its end and
+//! at its start, divided by its length. A sale weighs in for as long as it remains the last one.
+//!
+//! The recorded price of a sale is capped to `TWAP_MAX_PRICE_STEP_BPS` above or below the previous
+//! one, so moving the average far takes many sales, each paying the market fee, spread over the
+//! window. Callers also choose how many sales a window must hold. The average still only reflects
+//! the sales made here, and the first sale of a collection is recorded as is.
+use alloc::string::String;
+
+use casper_contract::{contract_api::runtime, unwrap_or_revert::UnwrapOrRevert};
+use casper_types::{bytesrepr::ToBytes, Key, U256};
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{
+    get_dictionary_value_from_key, make_dictionary_item_key_for_key,
+    write_dictionary_value_from_key,
+};
+
+type Observation = (u64, U256, U256);
+
+// Next slot to write and number of observations stored.
+type Ring = (u32, u32);
+
+fn observation_key(nft_contract: Key, slot: u32) -> String {
+    let mut preimage = nft_contract.to_bytes().unwrap_or_revert();
+    preimage.extend(slot.to_bytes().unwrap_or_revert());
+    hex::encode(runtime::blake2b(preimage))
+}
+
+fn ring(nft_contract: Key) -> Ring {
+    get_dictionary_value_from_key::<Ring>(
+        TWAP_RING,
+        &make_dictionary_item_key_for_key(nft_contract),
+    )
+    .unwrap_or_default()
+}
+
+fn observation(nft_contract: Key, slot: u32) -> Observation {
+    get_dictionary_value_from_key::<Observation>(
+        TWAP_OBSERVATIONS,
+        &observation_key(nft_contract, slot),
+    )
+    .unwrap_or_revert()
+}
+
+// Cumulative price at `timestamp`, which may not precede the observation.
+fn cumulative_at(observation: Observation, timestamp: u64) -> U256 {
+    let (observed_at, cumulative, price) = observation;
+    cumulative + price * U256::from(timestamp - observed_at)
+}
+
+// Keeps `price` within `TWAP_MAX_PRICE_STEP_BPS` of the previous recorded price. A free sale
+// leaves nothing to cap from.
+fn capped_price(last_price: U256, price: U256) -> U256 {
+    if last_price.is_zero() {
+        return price;
+    }
+    let step = last_price * U256::from(TWAP_MAX_PRICE_STEP_BPS) / U256::from(BPS_DIVISOR);
+    price.max(last_price - step).min(last_price + step)
+}
+
+/// Adds a sale of the collection at the current block time.
+pub(crate) fn record(nft_contract: Key, price: U256) {
+    let now = u64::from(runtime::get_blocktime());
+    let (next, count) = ring(nft_contract);
+    let (cumulative, price) = if count == 0 {
+        (U256::zero(), price)
+    } else {
+        let last = (next + TWAP_OBSERVATION_SLOTS - 1) % TWAP_OBSERVATION_SLOTS;
+        let last_observation = observation(nft_contract, last);
+        let (_, _, last_price) = last_observation;
+        (
+            cumulative_at(last_observation, now),
+            capped_price(last_price, price),
+        )
+    };
+
+    write_dictionary_value_from_key(
+        TWAP_OBSERVATIONS,
+        &observation_key(nft_contract, next),
+        (now, cumulative, price),
+    );
+    write_dictionary_value_from_key(
+        TWAP_RING,
+        &make_dictionary_item_key_for_key(nft_contract),
+        (
+            (next + 1) % TWAP_OBSERVATION_SLOTS,
+            (count + 1).min(TWAP_OBSERVATION_SLOTS),
+        ),
+    );
+}
+
+/// Returns the average recorded sale price of the collection over the last `window` milliseconds.
+///
+/// Reverts when the oldest stored observation is more recent than the start of the window, or
+/// when fewer than `min_sales` sales were made within the window.
+pub(crate) fn twap(nft_contract: Key, window: u64, min_sales: u32) -> U256 {
+    if window == 0 {
+        runtime::revert(Error::InvalidTwapWindow);
+    }
+    let now = u64::from(runtime::get_blocktime());
+    let start = now
+        .checked_sub(window)
+        .unwrap_or_revert_with(Error::TwapWindowTooLong);
+
+    let (next, count) = ring(nft_contract);
+    let oldest = if count < TWAP_OBSERVATION_SLOTS {
+        0
+    } else {
+        next
+    };
+    // observations from oldest to newest
+    let nth = |n: u32| observation(nft_contract, (oldest + n) % TWAP_OBSERVATION_SLOTS);
+
+    if count == 0 || nth(0).0 > start {
+        runtime::revert(Error::TwapWindowTooLong);
+    }
+
+    // newest observation made at or before the start of the window
+    let (mut low, mut high) = (0, count - 1);
+    while low < high {
+        let mid = (low + high + 1) / 2;
+        if nth(mid).0 <= start {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    if count - 1 - low < min_sales {
+        runtime::revert(Error::TwapTooFewSales);
+    }
+
+    let end_cumulative = cumulative_at(nth(count - 1), now);
+    let start_cumulative = cumulative_at(nth(low), start);
+    (end_cumulative - start_cumulative) / U256::from(window)
+}
@@ -3,7 +3,13 @@ use alloc::{string::String, vec};
 use crate::constants::*;
 use alloc::boxed::Box;
 
-use casper_types::{CLType, EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Parameter};
+use casper_types::{
+    CLType, CLTyped, EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Parameter,
+};
+
+use crate::listing_index::ListingPage;
+use crate::market_stats::{CollectionStats, Floor, LastSale};
+use crate::{CollectionConfig, MarketConfig, SellingInMarketV1};
 
 fn transfer_owner() -> EntryPoint {
     EntryPoint::new(
@@ -14,6 +20,37 @@ fn transfer_owner() -> EntryPoint {
         EntryPointType::Contract,
     )
 }
+
+fn propose_owner() -> EntryPoint {
+    EntryPoint::new(
+        String::from(PROPOSE_OWNER_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_CONTRACT_OWNER, CLType::Key)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn accept_ownership() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ACCEPT_OWNERSHIP_ENTRY_POINT_NAME),
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn cancel_ownership_transfer() -> EntryPoint {
+    EntryPoint::new(
+        String::from(CANCEL_OWNERSHIP_TRANSFER_ENTRY_POINT_NAME),
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
 fn change_fee() -> EntryPoint {
     EntryPoint::new(
         String::from(CHANGE_FEE_ENTRY_POINT_NAME),
@@ -138,10 +175,23 @@ fn change_price() -> EntryPoint {
     )
 }
 
+fn emergency_shutdown() -> EntryPoint {
+    EntryPoint::new(
+        String::from(EMERGENCY_SHUTDOWN_ENTRY_POINT_NAME),
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
 fn emergency_withdraw_cspr() -> EntryPoint {
     EntryPoint::new(
         String::from(EMEGENCY_WITHDRAW_CSPR),
-        vec![Parameter::new(AMOUNT_RUNTIME_ARG_NAME, CLType::U256)],
+        vec![
+            Parameter::new(ARG_TOKEN_IDS, CLType::List(Box::new(CLType::String))),
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+        ],
         CLType::Unit,
         EntryPointAccess::Public,
         EntryPointType::Contract,
@@ -167,6 +217,7 @@ fn set_support_token() -> EntryPoint {
         vec![
             Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
             Parameter::new(ARG_NFT_ENABLED, CLType::Bool),
+            Parameter::new(ARG_NFT_STANDARD, CLType::U8),
         ],
         CLType::Unit,
         EntryPointAccess::Public,
@@ -174,6 +225,51 @@ fn set_support_token() -> EntryPoint {
     )
 }
 
+fn set_bid_config() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_BID_CONFIG_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_MIN_BID, CLType::U256),
+            Parameter::new(ARG_MIN_BID_INCREMENT, CLType::U256),
+            Parameter::new(ARG_MIN_BID_INCREMENT_BPS, CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_referral_share() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_REFERRAL_SHARE_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_REFERRAL_SHARE_BPS, CLType::U256)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_referral_total() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_REFERRAL_TOTAL_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_REFERRER, CLType::Key)],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_max_bids_per_token() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_MAX_BIDS_PER_TOKEN_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_MAX_BIDS_PER_TOKEN, CLType::U32)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
 fn init() -> EntryPoint {
     EntryPoint::new(
         String::from(INIT_ENTRY_POINT_NAME),
@@ -182,6 +278,59 @@ fn init() -> EntryPoint {
             Parameter::new(ARG_CONTRACT_OWNER, CLType::Key),
             Parameter::new(ARG_MARKET_FEE_RECEIVER, CLType::Key),
             Parameter::new(ARG_MARKET_FEE, CLType::U256),
+            Parameter::new(ARG_PACKAGE_ACCESS, CLType::URef),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn migrate() -> EntryPoint {
+    EntryPoint::new(
+        String::from(MIGRATE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_CONTRACT_VERSION, CLType::U32),
+            Parameter::new(ARG_PACKAGE_ACCESS, CLType::URef),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_fee_receiver() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_FEE_RECEIVER_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_MARKET_FEE_RECEIVER, CLType::Key)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_fee_recipients() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_FEE_RECIPIENTS_ENTRY_POINT_NAME),
+        vec![Parameter::new(
+            ARG_FEE_RECIPIENTS,
+            CLType::List(Box::new(CLType::Tuple2([
+                Box::new(CLType::Key),
+                Box::new(CLType::U256),
+            ]))),
+        )],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_fee_override() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_FEE_OVERRIDE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_ROUTE, CLType::U8),
+            Parameter::new(ARG_FEE_BPS, CLType::Option(Box::new(CLType::U256))),
         ],
         CLType::Unit,
         EntryPointAccess::Public,
@@ -189,11 +338,323 @@ fn init() -> EntryPoint {
     )
 }
 
+fn get_effective_fee() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_EFFECTIVE_FEE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_ROUTE, CLType::U8),
+        ],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn execute_config_change() -> EntryPoint {
+    EntryPoint::new(
+        String::from(EXECUTE_CONFIG_CHANGE_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_CONFIG, CLType::U8)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn cancel_config_change() -> EntryPoint {
+    EntryPoint::new(
+        String::from(CANCEL_CONFIG_CHANGE_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_CONFIG, CLType::U8)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn grant_role() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GRANT_ROLE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_ACCOUNT, CLType::Key),
+            Parameter::new(ARG_ROLE, CLType::U8),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn revoke_role() -> EntryPoint {
+    EntryPoint::new(
+        String::from(REVOKE_ROLE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_ACCOUNT, CLType::Key),
+            Parameter::new(ARG_ROLE, CLType::U8),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_paused() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_PAUSED_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_PAUSED, CLType::U8)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_collection_paused() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_COLLECTION_PAUSED_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_PAUSED, CLType::U8),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_migration_source() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_MIGRATION_SOURCE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_SOURCE_MARKETPLACE, CLType::Key),
+            Parameter::new(ARG_ENABLED, CLType::Bool),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_migration_target() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_MIGRATION_TARGET_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_TARGET_MARKETPLACE, CLType::Key)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn clear_migration_target() -> EntryPoint {
+    EntryPoint::new(
+        String::from(CLEAR_MIGRATION_TARGET_ENTRY_POINT_NAME),
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn migrate_listings() -> EntryPoint {
+    EntryPoint::new(
+        String::from(MIGRATE_LISTINGS_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_TARGET_MARKETPLACE, CLType::Key),
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_IDS, CLType::List(Box::new(CLType::String))),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn import_listing() -> EntryPoint {
+    EntryPoint::new(
+        String::from(IMPORT_LISTING_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::String),
+            Parameter::new(ARG_SELLER, CLType::Option(Box::new(CLType::Key))),
+            Parameter::new(ARG_MINIMUM_OFFER, CLType::U256),
+            Parameter::new(ARG_IS_ACTIVE, CLType::Bool),
+            Parameter::new(
+                ARG_BIDS,
+                CLType::List(Box::new(CLType::Tuple2([
+                    Box::new(CLType::Key),
+                    Box::new(CLType::U256),
+                ]))),
+            ),
+            Parameter::new(ARG_SRC_PURSE, CLType::URef),
+        ],
+        CLType::Key,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
 /// Returns the default set of ERC20 token entry points.
+fn get_listing() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_LISTING_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::String),
+        ],
+        CLType::Option(Box::new(SellingInMarketV1::cl_type())),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_bid() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_BID_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::String),
+            Parameter::new(ARG_BIDDER, CLType::Key),
+        ],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_top_bids() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_TOP_BIDS_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::String),
+            Parameter::new(ARG_COUNT, CLType::U32),
+        ],
+        CLType::List(Box::new(CLType::Tuple2([
+            Box::new(CLType::Key),
+            Box::new(CLType::U256),
+        ]))),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_market_config() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_MARKET_CONFIG_ENTRY_POINT_NAME),
+        vec![],
+        MarketConfig::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_collection_config() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_COLLECTION_CONFIG_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key)],
+        CollectionConfig::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_seller_listings() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_SELLER_LISTINGS_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_SELLER, CLType::Key),
+            Parameter::new(ARG_OFFSET, CLType::U32),
+            Parameter::new(ARG_LIMIT, CLType::U32),
+        ],
+        ListingPage::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_collection_listings() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_COLLECTION_LISTINGS_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_OFFSET, CLType::U32),
+            Parameter::new(ARG_LIMIT, CLType::U32),
+        ],
+        ListingPage::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn index_listings() -> EntryPoint {
+    EntryPoint::new(
+        String::from(INDEX_LISTINGS_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_IDS, CLType::List(Box::new(CLType::String))),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_collection_stats() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_COLLECTION_STATS_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key)],
+        <(CollectionStats, Floor)>::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn refresh_floor() -> EntryPoint {
+    EntryPoint::new(
+        String::from(REFRESH_FLOOR_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_LIMIT, CLType::U32),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_last_sale() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_LAST_SALE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::String),
+        ],
+        LastSale::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_twap() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_TWAP_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_WINDOW, CLType::U64),
+            Parameter::new(ARG_MIN_SALES, CLType::U32),
+        ],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
 pub(crate) fn default() -> EntryPoints {
     let mut entry_points = EntryPoints::new();
     entry_points.add_entry_point(transfer_owner());
+    entry_points.add_entry_point(propose_owner());
+    entry_points.add_entry_point(accept_ownership());
+    entry_points.add_entry_point(cancel_ownership_transfer());
     entry_points.add_entry_point(init());
+    entry_points.add_entry_point(migrate());
     entry_points.add_entry_point(change_fee());
     entry_points.add_entry_point(revoke_sell());
     entry_points.add_entry_point(sell());
@@ -203,8 +664,40 @@ pub(crate) fn default() -> EntryPoints {
     entry_points.add_entry_point(bid());
     entry_points.add_entry_point(revoke_bid());
     entry_points.add_entry_point(increase_bid());
+    entry_points.add_entry_point(emergency_shutdown());
     entry_points.add_entry_point(emergency_withdraw_nfts());
     entry_points.add_entry_point(emergency_withdraw_cspr());
     entry_points.add_entry_point(accept_price());
+    entry_points.add_entry_point(set_bid_config());
+    entry_points.add_entry_point(set_max_bids_per_token());
+    entry_points.add_entry_point(set_fee_receiver());
+    entry_points.add_entry_point(execute_config_change());
+    entry_points.add_entry_point(cancel_config_change());
+    entry_points.add_entry_point(grant_role());
+    entry_points.add_entry_point(revoke_role());
+    entry_points.add_entry_point(set_paused());
+    entry_points.add_entry_point(set_collection_paused());
+    entry_points.add_entry_point(set_migration_source());
+    entry_points.add_entry_point(migrate_listings());
+    entry_points.add_entry_point(import_listing());
+    entry_points.add_entry_point(get_listing());
+    entry_points.add_entry_point(get_bid());
+    entry_points.add_entry_point(get_top_bids());
+    entry_points.add_entry_point(get_market_config());
+    entry_points.add_entry_point(get_collection_config());
+    entry_points.add_entry_point(get_seller_listings());
+    entry_points.add_entry_point(get_collection_listings());
+    entry_points.add_entry_point(index_listings());
+    entry_points.add_entry_point(get_collection_stats());
+    entry_points.add_entry_point(get_last_sale());
+    entry_points.add_entry_point(get_twap());
+    entry_points.add_entry_point(set_referral_share());
+    entry_points.add_entry_point(get_referral_total());
+    entry_points.add_entry_point(set_fee_recipients());
+    entry_points.add_entry_point(set_fee_override());
+    entry_points.add_entry_point(get_effective_fee());
+    entry_points.add_entry_point(set_migration_target());
+    entry_points.add_entry_point(clear_migration_target());
+    entry_points.add_entry_point(refresh_floor());
     entry_points
 }
@@ -1,19 +1,28 @@
-#![allow(unused_parens)]
-#![allow(non_snake_case)]
-#![allow(dead_code)]
-
-// use std::collections::BTreeMap;
-
+//! Marketplace events, emitted following the Casper Event Standard (CES).
+//!
+//! Every event is appended to the `__events` dictionary under its sequence number, as the
+//! `event_`-prefixed event name followed by its fields serialized in schema order. Each event
+//! starts with its sequence number and the block time it was emitted at. The schemas are kept in
+//! `__events_schema` and the number of events emitted so far in `__events_length`.
 extern crate alloc;
 use alloc::{
+    boxed::Box,
     collections::BTreeMap,
     string::{String, ToString},
+    vec,
     vec::*,
 };
 
-use casper_contract::contract_api::storage;
-use casper_types::{ContractPackageHash, Key, URef, U256};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    bytesrepr::{self, Bytes, ToBytes},
+    CLType, CLTyped, Key, U256,
+};
 
+use crate::constants::*;
 use crate::helpers::*;
 
 pub enum MarketPlaceEvent {
@@ -37,6 +46,9 @@ pub enum MarketPlaceEvent {
         offeror: Key,
         buyer: Key,
         value: U256,
+        fee: U256,
+        seller_amount: U256,
+        route: u8,
     },
     ChangePrice {
         nft_contract: Key,
@@ -60,187 +72,709 @@ pub enum MarketPlaceEvent {
         value: U256,
         is_revoke: bool,
     },
+    ListingMigrated {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        minimum_offer: U256,
+        target_marketplace: Key,
+        bid_count: u32,
+        bid_total: U256,
+    },
+    ListingImported {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        minimum_offer: U256,
+        is_active: bool,
+        source_marketplace: Key,
+        bid_count: u32,
+        bid_total: U256,
+    },
+    RoleGranted {
+        account: Key,
+        role: u8,
+        sender: Key,
+    },
+    RoleRevoked {
+        account: Key,
+        role: u8,
+        sender: Key,
+    },
+    OwnershipTransferStarted {
+        previous_owner: Key,
+        new_owner: Key,
+    },
+    OwnershipTransferred {
+        previous_owner: Key,
+        new_owner: Key,
+    },
+    OwnershipTransferCancelled {
+        pending_owner: Key,
+        sender: Key,
+    },
+    ConfigChangeScheduled {
+        config: u8,
+        value: String,
+        effective_at: u64,
+    },
+    ConfigChangeExecuted {
+        config: u8,
+        value: String,
+    },
+    ConfigChangeCancelled {
+        config: u8,
+        value: String,
+    },
+    EmergencyShutdown {
+        sender: Key,
+    },
+    EmergencyNftReturned {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+    },
+    EmergencyBidRefunded {
+        nft_contract: Key,
+        token_id: String,
+        bidder: Key,
+        value: U256,
+    },
+    Refund {
+        nft_contract: Key,
+        token_id: String,
+        recipient: Key,
+        value: U256,
+        reason: u8,
+    },
+    CollectionUpdated {
+        nft_contract: Key,
+        nft_standard: u8,
+    },
+    PauseUpdated {
+        nft_contract: Option<Key>,
+        paused: u8,
+    },
+    BidConfigUpdated {
+        nft_contract: Key,
+        min_bid: U256,
+        min_bid_increment: U256,
+        min_bid_increment_bps: U256,
+    },
+    MaxBidsPerTokenUpdated {
+        max_bids_per_token: u32,
+    },
+    MigrationSourceUpdated {
+        source_marketplace: Key,
+        enabled: bool,
+    },
+    ContractUpgraded {
+        contract_version: u32,
+    },
+    ReferralPaid {
+        nft_contract: Key,
+        token_id: String,
+        referrer: Key,
+        value: U256,
+    },
+    ReferralShareUpdated {
+        referral_share_bps: U256,
+    },
+    FeeOverrideUpdated {
+        nft_contract: Option<Key>,
+        route: u8,
+        fee_bps: Option<U256>,
+    },
+    MigrationTargetCleared {
+        target_marketplace: Key,
+    },
 }
 
 impl MarketPlaceEvent {
-    pub fn type_name(&self) -> String {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarketPlaceEvent::Sell { .. } => "Sell",
+            MarketPlaceEvent::Revoke { .. } => "Revoke",
+            MarketPlaceEvent::DoneSell { .. } => "DoneSell",
+            MarketPlaceEvent::ChangePrice { .. } => "ChangePrice",
+            MarketPlaceEvent::Bid { .. } => "Bid",
+            MarketPlaceEvent::RevokeBid { .. } => "RevokeBid",
+            MarketPlaceEvent::ListingMigrated { .. } => "ListingMigrated",
+            MarketPlaceEvent::ListingImported { .. } => "ListingImported",
+            MarketPlaceEvent::RoleGranted { .. } => "RoleGranted",
+            MarketPlaceEvent::RoleRevoked { .. } => "RoleRevoked",
+            MarketPlaceEvent::OwnershipTransferStarted { .. } => "OwnershipTransferStarted",
+            MarketPlaceEvent::OwnershipTransferred { .. } => "OwnershipTransferred",
+            MarketPlaceEvent::OwnershipTransferCancelled { .. } => "OwnershipTransferCancelled",
+            MarketPlaceEvent::ConfigChangeScheduled { .. } => "ConfigChangeScheduled",
+            MarketPlaceEvent::ConfigChangeExecuted { .. } => "ConfigChangeExecuted",
+            MarketPlaceEvent::ConfigChangeCancelled { .. } => "ConfigChangeCancelled",
+            MarketPlaceEvent::EmergencyShutdown { .. } => "EmergencyShutdown",
+            MarketPlaceEvent::EmergencyNftReturned { .. } => "EmergencyNftReturned",
+            MarketPlaceEvent::EmergencyBidRefunded { .. } => "EmergencyBidRefunded",
+            MarketPlaceEvent::Refund { .. } => "Refund",
+            MarketPlaceEvent::CollectionUpdated { .. } => "CollectionUpdated",
+            MarketPlaceEvent::PauseUpdated { .. } => "PauseUpdated",
+            MarketPlaceEvent::BidConfigUpdated { .. } => "BidConfigUpdated",
+            MarketPlaceEvent::MaxBidsPerTokenUpdated { .. } => "MaxBidsPerTokenUpdated",
+            MarketPlaceEvent::MigrationSourceUpdated { .. } => "MigrationSourceUpdated",
+            MarketPlaceEvent::ContractUpgraded { .. } => "ContractUpgraded",
+            MarketPlaceEvent::ReferralPaid { .. } => "ReferralPaid",
+            MarketPlaceEvent::ReferralShareUpdated { .. } => "ReferralShareUpdated",
+            MarketPlaceEvent::FeeOverrideUpdated { .. } => "FeeOverrideUpdated",
+            MarketPlaceEvent::MigrationTargetCleared { .. } => "MigrationTargetCleared",
+        }
+    }
+
+    fn to_bytes(&self, sequence: u32, timestamp: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        append(&mut bytes, &(EVENT_PREFIX.to_string() + self.name()));
+        append(&mut bytes, &sequence);
+        append(&mut bytes, &timestamp);
         match self {
             MarketPlaceEvent::Sell {
-                nft_contract: _,
-                token_id: _,
-                offeror: _,
-                minimum_offer: _,
-                is_active: _,
-            } => "sell",
-            MarketPlaceEvent::ChangePrice {
-                nft_contract: _,
-                token_id: _,
-                offeror: _,
-                new_price: _,
-                is_active: _,
-            } => "change_price",
-            MarketPlaceEvent::Revoke {
-                nft_contract: _,
-                token_id: _,
-                offeror: _,
-                minimum_offer: _,
-                is_active: _,
-            } => "revoke",
+                nft_contract,
+                token_id,
+                offeror,
+                minimum_offer,
+                is_active,
+            }
+            | MarketPlaceEvent::Revoke {
+                nft_contract,
+                token_id,
+                offeror,
+                minimum_offer,
+                is_active,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+                append(&mut bytes, minimum_offer);
+                append(&mut bytes, is_active);
+            }
             MarketPlaceEvent::DoneSell {
-                nft_contract: _,
-                token_id: _,
-                offeror: _,
-                buyer: _,
-                value: _,
-            } => "donesell",
+                nft_contract,
+                token_id,
+                offeror,
+                buyer,
+                value,
+                fee,
+                seller_amount,
+                route,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+                append(&mut bytes, buyer);
+                append(&mut bytes, value);
+                append(&mut bytes, fee);
+                append(&mut bytes, seller_amount);
+                append(&mut bytes, route);
+            }
+            MarketPlaceEvent::ChangePrice {
+                nft_contract,
+                token_id,
+                offeror,
+                new_price,
+                is_active,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+                append(&mut bytes, new_price);
+                append(&mut bytes, is_active);
+            }
             MarketPlaceEvent::Bid {
-                nft_contract: _,
-                token_id: _,
-                offeror: _,
-                bidder: _,
-                value: _,
-            } => "bid",
+                nft_contract,
+                token_id,
+                offeror,
+                bidder,
+                value,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+                append(&mut bytes, bidder);
+                append(&mut bytes, value);
+            }
             MarketPlaceEvent::RevokeBid {
-                nft_contract: _,
-                token_id: _,
-                offeror: _,
-                bidder: _,
-                value: _,
-                is_revoke: _,
-            } => "revokebid",
+                nft_contract,
+                token_id,
+                offeror,
+                bidder,
+                value,
+                is_revoke,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+                append(&mut bytes, bidder);
+                append(&mut bytes, value);
+                append(&mut bytes, is_revoke);
+            }
+            MarketPlaceEvent::ListingMigrated {
+                nft_contract,
+                token_id,
+                offeror,
+                minimum_offer,
+                target_marketplace,
+                bid_count,
+                bid_total,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+                append(&mut bytes, minimum_offer);
+                append(&mut bytes, target_marketplace);
+                append(&mut bytes, bid_count);
+                append(&mut bytes, bid_total);
+            }
+            MarketPlaceEvent::ListingImported {
+                nft_contract,
+                token_id,
+                offeror,
+                minimum_offer,
+                is_active,
+                source_marketplace,
+                bid_count,
+                bid_total,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+                append(&mut bytes, minimum_offer);
+                append(&mut bytes, is_active);
+                append(&mut bytes, source_marketplace);
+                append(&mut bytes, bid_count);
+                append(&mut bytes, bid_total);
+            }
+            MarketPlaceEvent::RoleGranted {
+                account,
+                role,
+                sender,
+            }
+            | MarketPlaceEvent::RoleRevoked {
+                account,
+                role,
+                sender,
+            } => {
+                append(&mut bytes, account);
+                append(&mut bytes, role);
+                append(&mut bytes, sender);
+            }
+            MarketPlaceEvent::OwnershipTransferStarted {
+                previous_owner,
+                new_owner,
+            }
+            | MarketPlaceEvent::OwnershipTransferred {
+                previous_owner,
+                new_owner,
+            } => {
+                append(&mut bytes, previous_owner);
+                append(&mut bytes, new_owner);
+            }
+            MarketPlaceEvent::OwnershipTransferCancelled {
+                pending_owner,
+                sender,
+            } => {
+                append(&mut bytes, pending_owner);
+                append(&mut bytes, sender);
+            }
+            MarketPlaceEvent::ConfigChangeScheduled {
+                config,
+                value,
+                effective_at,
+            } => {
+                append(&mut bytes, config);
+                append(&mut bytes, value);
+                append(&mut bytes, effective_at);
+            }
+            MarketPlaceEvent::ConfigChangeExecuted { config, value }
+            | MarketPlaceEvent::ConfigChangeCancelled { config, value } => {
+                append(&mut bytes, config);
+                append(&mut bytes, value);
+            }
+            MarketPlaceEvent::EmergencyShutdown { sender } => {
+                append(&mut bytes, sender);
+            }
+            MarketPlaceEvent::EmergencyNftReturned {
+                nft_contract,
+                token_id,
+                offeror,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, offeror);
+            }
+            MarketPlaceEvent::EmergencyBidRefunded {
+                nft_contract,
+                token_id,
+                bidder,
+                value,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, bidder);
+                append(&mut bytes, value);
+            }
+            MarketPlaceEvent::Refund {
+                nft_contract,
+                token_id,
+                recipient,
+                value,
+                reason,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, recipient);
+                append(&mut bytes, value);
+                append(&mut bytes, reason);
+            }
+            MarketPlaceEvent::CollectionUpdated {
+                nft_contract,
+                nft_standard,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, nft_standard);
+            }
+            MarketPlaceEvent::PauseUpdated {
+                nft_contract,
+                paused,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, paused);
+            }
+            MarketPlaceEvent::BidConfigUpdated {
+                nft_contract,
+                min_bid,
+                min_bid_increment,
+                min_bid_increment_bps,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, min_bid);
+                append(&mut bytes, min_bid_increment);
+                append(&mut bytes, min_bid_increment_bps);
+            }
+            MarketPlaceEvent::MaxBidsPerTokenUpdated { max_bids_per_token } => {
+                append(&mut bytes, max_bids_per_token);
+            }
+            MarketPlaceEvent::MigrationSourceUpdated {
+                source_marketplace,
+                enabled,
+            } => {
+                append(&mut bytes, source_marketplace);
+                append(&mut bytes, enabled);
+            }
+            MarketPlaceEvent::ContractUpgraded { contract_version } => {
+                append(&mut bytes, contract_version);
+            }
+            MarketPlaceEvent::ReferralPaid {
+                nft_contract,
+                token_id,
+                referrer,
+                value,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, token_id);
+                append(&mut bytes, referrer);
+                append(&mut bytes, value);
+            }
+            MarketPlaceEvent::ReferralShareUpdated { referral_share_bps } => {
+                append(&mut bytes, referral_share_bps);
+            }
+            MarketPlaceEvent::FeeOverrideUpdated {
+                nft_contract,
+                route,
+                fee_bps,
+            } => {
+                append(&mut bytes, nft_contract);
+                append(&mut bytes, route);
+                append(&mut bytes, fee_bps);
+            }
+            MarketPlaceEvent::MigrationTargetCleared { target_marketplace } => {
+                append(&mut bytes, target_marketplace);
+            }
         }
-        .to_string()
+        bytes
     }
 }
 
-pub fn contract_package_hash() -> ContractPackageHash {
-    get_key::<ContractPackageHash>("contract_package_hash").unwrap()
+fn append<T: ToBytes>(bytes: &mut Vec<u8>, value: &T) {
+    bytes.extend(value.to_bytes().unwrap_or_revert());
 }
 
-// pub(crate) fn contract_package_hash() -> ContractPackageHash {
-//     let key : Key = runtime::get_key("contract_package_hash").unwrap();
-//     let contract_package_hash_addr: HashAddr = key.into_hash().unwrap();
-//     let factory_package_hash: ContractPackageHash = ContractPackageHash::new(contract_package_hash_addr);
-//     factory_package_hash
-
-// }
-
-pub(crate) fn emit(pair_event: &MarketPlaceEvent) {
-    let mut events = Vec::new();
-    // let package : ContractPackageHash = runtime::get_key("contract_package_hash").into_hash().unwrap_or_revert();
-    let package = contract_package_hash();
-    match pair_event {
-        MarketPlaceEvent::Sell {
-            nft_contract,
-            token_id,
-            offeror,
-            minimum_offer,
-            is_active,
-        } => {
-            let mut event = BTreeMap::new();
-            event.insert("contract_package_hash", package.to_string());
-            event.insert("event_type", pair_event.type_name());
-            event.insert("nft_contract", nft_contract.to_string());
-            event.insert("token_id", token_id.to_string());
-            event.insert("offeror", offeror.to_string());
-            event.insert("minimum_offer", minimum_offer.to_string());
-            event.insert("is_active", is_active.to_string());
-            events.push(event);
-        }
+fn field(name: &str, cl_type: CLType) -> (String, CLType) {
+    (name.to_string(), cl_type)
+}
 
-        MarketPlaceEvent::ChangePrice {
-            nft_contract,
-            token_id,
-            offeror,
-            new_price,
-            is_active,
-        } => {
-            let mut event = BTreeMap::new();
-            event.insert("contract_package_hash", package.to_string());
-            event.insert("event_type", pair_event.type_name());
-            event.insert("nft_contract", nft_contract.to_string());
-            event.insert("token_id", token_id.to_string());
-            event.insert("offeror", offeror.to_string());
-            event.insert("new_price", new_price.to_string());
-            event.insert("is_active", is_active.to_string());
-            events.push(event);
-        }
+/// Event schemas in the layout CES tooling reads from `__events_schema`: event name to its
+/// fields, in payload order.
+struct Schemas(BTreeMap<String, Vec<(String, CLType)>>);
 
-        MarketPlaceEvent::Revoke {
-            nft_contract,
-            token_id,
-            offeror,
-            minimum_offer,
-            is_active,
-        } => {
-            let mut event = BTreeMap::new();
-            event.insert("contract_package_hash", package.to_string());
-            event.insert("event_type", pair_event.type_name());
-            event.insert("nft_contract", nft_contract.to_string());
-            event.insert("token_id", token_id.to_string());
-            event.insert("offeror", offeror.to_string());
-            event.insert("minimum_offer", minimum_offer.to_string());
-            event.insert("is_active", is_active.to_string());
-            events.push(event);
-        }
+impl ToBytes for Schemas {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        self.0.to_bytes()
+    }
 
-        MarketPlaceEvent::DoneSell {
-            nft_contract,
-            token_id,
-            offeror,
-            buyer,
-            value,
-        } => {
-            let mut event = BTreeMap::new();
-            event.insert("contract_package_hash", package.to_string());
-            event.insert("event_type", pair_event.type_name());
-            event.insert("nft_contract", nft_contract.to_string());
-            event.insert("token_id", token_id.to_string());
-            event.insert("offeror", offeror.to_string());
-            event.insert("buyer", buyer.to_string());
-            event.insert("value", value.to_string());
-            events.push(event);
-        }
+    fn serialized_length(&self) -> usize {
+        self.0.serialized_length()
+    }
+}
 
-        MarketPlaceEvent::Bid {
-            nft_contract,
-            token_id,
-            offeror,
-            bidder,
-            value,
-        } => {
-            let mut event = BTreeMap::new();
-            event.insert("contract_package_hash", package.to_string());
-            event.insert("event_type", pair_event.type_name());
-            event.insert("nft_contract", nft_contract.to_string());
-            event.insert("token_id", token_id.to_string());
-            event.insert("offeror", offeror.to_string());
-            event.insert("bidder", bidder.to_string());
-            event.insert("value", value.to_string());
-            events.push(event);
-        }
+impl CLTyped for Schemas {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
 
-        MarketPlaceEvent::RevokeBid {
-            nft_contract,
-            token_id,
-            offeror,
-            bidder,
-            value,
-            is_revoke,
-        } => {
-            let mut event = BTreeMap::new();
-            event.insert("contract_package_hash", package.to_string());
-            event.insert("event_type", pair_event.type_name());
-            event.insert("nft_contract", nft_contract.to_string());
-            event.insert("token_id", token_id.to_string());
-            event.insert("offeror", offeror.to_string());
-            event.insert("bidder", bidder.to_string());
-            event.insert("value", value.to_string());
-            event.insert("is_revoke", is_revoke.to_string());
-            events.push(event);
-        }
+fn schemas() -> Schemas {
+    let listing = || {
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+            field("minimum_offer", CLType::U256),
+            field("is_active", CLType::Bool),
+        ]
     };
-    for event in events {
-        let _: URef = storage::new_uref(event);
+    let role = || {
+        vec![
+            field("account", CLType::Key),
+            field("role", CLType::U8),
+            field("sender", CLType::Key),
+        ]
+    };
+    let ownership = || {
+        vec![
+            field("previous_owner", CLType::Key),
+            field("new_owner", CLType::Key),
+        ]
+    };
+    let config_change = || vec![field("config", CLType::U8), field("value", CLType::String)];
+
+    let mut schemas = BTreeMap::new();
+    schemas.insert("Sell".to_string(), listing());
+    schemas.insert("Revoke".to_string(), listing());
+    schemas.insert(
+        "DoneSell".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+            field("buyer", CLType::Key),
+            field("value", CLType::U256),
+            field("fee", CLType::U256),
+            field("seller_amount", CLType::U256),
+            field("route", CLType::U8),
+        ],
+    );
+    schemas.insert(
+        "ChangePrice".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+            field("new_price", CLType::U256),
+            field("is_active", CLType::Bool),
+        ],
+    );
+    schemas.insert(
+        "Bid".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+            field("bidder", CLType::Key),
+            field("value", CLType::U256),
+        ],
+    );
+    schemas.insert(
+        "RevokeBid".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+            field("bidder", CLType::Key),
+            field("value", CLType::U256),
+            field("is_revoke", CLType::Bool),
+        ],
+    );
+    schemas.insert(
+        "ListingMigrated".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+            field("minimum_offer", CLType::U256),
+            field("target_marketplace", CLType::Key),
+            field("bid_count", CLType::U32),
+            field("bid_total", CLType::U256),
+        ],
+    );
+    schemas.insert(
+        "ListingImported".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+            field("minimum_offer", CLType::U256),
+            field("is_active", CLType::Bool),
+            field("source_marketplace", CLType::Key),
+            field("bid_count", CLType::U32),
+            field("bid_total", CLType::U256),
+        ],
+    );
+    schemas.insert("RoleGranted".to_string(), role());
+    schemas.insert("RoleRevoked".to_string(), role());
+    schemas.insert("OwnershipTransferStarted".to_string(), ownership());
+    schemas.insert("OwnershipTransferred".to_string(), ownership());
+    schemas.insert(
+        "OwnershipTransferCancelled".to_string(),
+        vec![
+            field("pending_owner", CLType::Key),
+            field("sender", CLType::Key),
+        ],
+    );
+    schemas.insert(
+        "ConfigChangeScheduled".to_string(),
+        vec![
+            field("config", CLType::U8),
+            field("value", CLType::String),
+            field("effective_at", CLType::U64),
+        ],
+    );
+    schemas.insert("ConfigChangeExecuted".to_string(), config_change());
+    schemas.insert("ConfigChangeCancelled".to_string(), config_change());
+    schemas.insert(
+        "EmergencyShutdown".to_string(),
+        vec![field("sender", CLType::Key)],
+    );
+    schemas.insert(
+        "EmergencyNftReturned".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("offeror", CLType::Key),
+        ],
+    );
+    schemas.insert(
+        "EmergencyBidRefunded".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("bidder", CLType::Key),
+            field("value", CLType::U256),
+        ],
+    );
+    schemas.insert(
+        "Refund".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("recipient", CLType::Key),
+            field("value", CLType::U256),
+            field("reason", CLType::U8),
+        ],
+    );
+    schemas.insert(
+        "CollectionUpdated".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("nft_standard", CLType::U8),
+        ],
+    );
+    schemas.insert(
+        "PauseUpdated".to_string(),
+        vec![
+            field("nft_contract", CLType::Option(Box::new(CLType::Key))),
+            field("paused", CLType::U8),
+        ],
+    );
+    schemas.insert(
+        "BidConfigUpdated".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("min_bid", CLType::U256),
+            field("min_bid_increment", CLType::U256),
+            field("min_bid_increment_bps", CLType::U256),
+        ],
+    );
+    schemas.insert(
+        "MaxBidsPerTokenUpdated".to_string(),
+        vec![field("max_bids_per_token", CLType::U32)],
+    );
+    schemas.insert(
+        "MigrationSourceUpdated".to_string(),
+        vec![
+            field("source_marketplace", CLType::Key),
+            field("enabled", CLType::Bool),
+        ],
+    );
+    schemas.insert(
+        "ContractUpgraded".to_string(),
+        vec![field("contract_version", CLType::U32)],
+    );
+    schemas.insert(
+        "ReferralPaid".to_string(),
+        vec![
+            field("nft_contract", CLType::Key),
+            field("token_id", CLType::String),
+            field("referrer", CLType::Key),
+            field("value", CLType::U256),
+        ],
+    );
+    schemas.insert(
+        "ReferralShareUpdated".to_string(),
+        vec![field("referral_share_bps", CLType::U256)],
+    );
+    schemas.insert(
+        "FeeOverrideUpdated".to_string(),
+        vec![
+            field("nft_contract", CLType::Option(Box::new(CLType::Key))),
+            field("route", CLType::U8),
+            field("fee_bps", CLType::Option(Box::new(CLType::U256))),
+        ],
+    );
+    schemas.insert(
+        "MigrationTargetCleared".to_string(),
+        vec![field("target_marketplace", CLType::Key)],
+    );
+
+    // every event starts with its sequence number and block time
+    for fields in schemas.values_mut() {
+        fields.splice(
+            0..0,
+            vec![
+                field("sequence", CLType::U32),
+                field("timestamp", CLType::U64),
+            ],
+        );
+    }
+    Schemas(schemas)
+}
+
+/// Creates the CES named keys, or refreshes the schema after an upgrade while keeping the events
+/// emitted so far.
+pub(crate) fn init() {
+    ensure_dictionary(EVENTS);
+    if runtime::get_key(EVENTS_LENGTH).is_none() {
+        runtime::put_key(EVENTS_LENGTH, storage::new_uref(0u32).into());
     }
+    set_key(EVENTS_SCHEMA, schemas());
+    set_key(EVENTS_CES_VERSION, CES_VERSION.to_string());
+}
+
+pub(crate) fn emit(event: &MarketPlaceEvent) {
+    let events_uref = get_uref(EVENTS);
+    let events_length_uref = get_uref(EVENTS_LENGTH);
+    let events_length: u32 = storage::read(events_length_uref)
+        .unwrap_or_revert()
+        .unwrap_or_revert();
+
+    storage::dictionary_put(
+        events_uref,
+        &events_length.to_string(),
+        Bytes::from(event.to_bytes(events_length, u64::from(runtime::get_blocktime()))),
+    );
+    storage::write(events_length_uref, events_length + 1);
 }
@@ -1,8 +1,8 @@
 use crate::address::Address;
-use crate::constants::CONTRACT_HASH_KEY_NAME;
+use crate::constants::TOKEN_CONTRACT_SUPPORT;
 use crate::error;
 use crate::error::Error;
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::{vec, vec::Vec};
 use casper_contract::{
     contract_api::{self},
@@ -45,9 +45,12 @@ pub(crate) fn get_key_from_address(addr: &Address) -> Key {
     }
 }
 
+// Escrowed tokens are held under the package hash, which every contract version shares, so an
+// upgraded version can still move them.
+// The package hash, which every contract version shares.
 pub(crate) fn get_self_key() -> Key {
-    let contract_hash: Key = get_key(CONTRACT_HASH_KEY_NAME).unwrap_or_revert();
-    contract_hash
+    let self_addr = get_self_address().unwrap_or_revert();
+    get_key_from_address(&self_addr)
 }
 
 pub(crate) fn set_key<T: ToBytes + CLTyped>(name: &str, value: T) {
@@ -63,6 +66,34 @@ pub(crate) fn set_key<T: ToBytes + CLTyped>(name: &str, value: T) {
     }
 }
 
+pub(crate) fn get_self_address() -> Result<Address, Error> {
+    get_last_call_stack_item()
+        .map(call_stack_element_to_address)
+        .ok_or(Error::InvalidContext)
+}
+
+/// Returns the hash of the running contract version.
+pub(crate) fn get_self_contract_key() -> Key {
+    match get_last_call_stack_item() {
+        Some(CallStackElement::StoredContract { contract_hash, .. }) => {
+            Key::Hash(contract_hash.value())
+        }
+        _ => runtime::revert(Error::InvalidContext),
+    }
+}
+
+fn get_last_call_stack_item() -> Option<CallStackElement> {
+    let call_stack = runtime::get_call_stack();
+    call_stack.into_iter().rev().next()
+}
+
+/// Creates the dictionary `name` unless the contract already has it.
+pub(crate) fn ensure_dictionary(name: &str) {
+    if runtime::get_key(name).is_none() {
+        storage::new_dictionary(name).unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    }
+}
+
 /// Gets the immediate call stack element of the current execution.
 fn get_immediate_call_stack_item() -> Option<CallStackElement> {
     let call_stack = runtime::get_call_stack();
@@ -202,8 +233,11 @@ fn make_dictionary_item_key(owner: Address) -> String {
 
 /// Creates a dictionary item key for a dictionary item.
 #[no_mangle]
-pub(crate) fn make_dictionary_item_key_for_key(contract_hash: Key) -> String {
-    let preimage = contract_hash.into_hash().unwrap_or_revert();
+pub(crate) fn make_dictionary_item_key_for_key(key: Key) -> String {
+    let preimage = match key {
+        Key::Account(account_hash) => account_hash.value(),
+        _ => key.into_hash().unwrap_or_revert(),
+    };
     // NOTE: As for now dictionary item keys are limited to 64 characters only. Instead of using
     // hashing (which will effectively hash a hash) we'll use base64. Preimage is about 33 bytes for
     // both Address variants, and approximated base64-encoded length will be 4 * (33 / 3) ~ 44
@@ -373,14 +407,19 @@ pub(crate) fn write_dictionary_value_from_key<T: CLTyped + FromBytes + ToBytes>(
 //     hex::encode(&key_bytes)
 // }
 
-// pub(crate) fn get_token_market_key(nft_contract_hash: &Key, token_id: &String) -> String {
-//     let nft_contract_hash_binding = nft_contract_hash.to_formatted_string();
-//     let nft_contract_hash_bytes = nft_contract_hash_binding.as_bytes();
-//     let token_identifier_binding = token_id.to_string();
-//     let token_identifier_bytes = token_identifier_binding.as_bytes();
-//     let key_bytes = runtime::blake2b([nft_contract_hash_bytes, token_identifier_bytes].concat());
-//     hex::encode(&key_bytes)
-// }
+/// Dictionary key of a listing. Listings of the collection the contract was installed for keep
+/// the plain token id, so records written before other collections were supported stay in place.
+pub(crate) fn get_token_market_key(nft_contract_hash: &Key, token_id: &str) -> String {
+    let native_contract_hash: Key = get_key(TOKEN_CONTRACT_SUPPORT).unwrap_or_revert();
+    if *nft_contract_hash == native_contract_hash {
+        return token_id.to_string();
+    }
+    let nft_contract_hash_binding = nft_contract_hash.to_formatted_string();
+    let nft_contract_hash_bytes = nft_contract_hash_binding.as_bytes();
+    let token_identifier_bytes = token_id.as_bytes();
+    let key_bytes = runtime::blake2b([nft_contract_hash_bytes, token_identifier_bytes].concat());
+    hex::encode(&key_bytes)
+}
 
 pub fn u256_to_u512(nb: U256) -> U512 {
     let mut b = [0u8; 32];
@@ -8,34 +8,40 @@ compile_error!("target arch should be wasm32: compile with '--target wasm32-unkn
 extern crate alloc;
 
 mod address;
+mod bid_book;
 pub mod constants;
 mod entry_points;
 mod error;
 mod events;
 mod helpers;
+mod listing_index;
+mod market_stats;
 pub mod named_keys;
+mod roles;
+mod twap;
 use crate::constants::*;
 use crate::error::Error;
 use crate::helpers::*;
 use alloc::{
     string::{String, ToString},
+    vec,
     vec::*,
 };
 use casper_contract::{
     contract_api::{
         runtime, storage,
-        system::{transfer_from_purse_to_account, transfer_from_purse_to_purse},
+        system::{self, transfer_from_purse_to_account, transfer_from_purse_to_purse},
     },
     unwrap_or_revert::UnwrapOrRevert,
 };
 use casper_types::{
     bytesrepr, bytesrepr::FromBytes, bytesrepr::ToBytes, contracts::NamedKeys, runtime_args,
-    CLType, CLTyped, ContractHash, ContractPackageHash, HashAddr, Key, RuntimeArgs, URef, U256,
+    CLType, CLTyped, CLValue, ContractHash, ContractPackageHash, HashAddr, Key, RuntimeArgs, URef,
+    URefAddr, U256,
 };
 use events::MarketPlaceEvent;
-use helpers::{get_immediate_caller_key, get_self_key};
+use helpers::{get_immediate_caller_key, get_self_contract_key, get_self_key};
 use serde::{Deserialize, Serialize};
-const _FEE_DIVISOR: u64 = 10000;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct SellingInMarket {
@@ -44,36 +50,46 @@ pub(crate) struct SellingInMarket {
     offeror: Option<Key>, //token seller
     minimum_offer: U256,  // min price in WCSPR
     is_active: bool,
-    bidder: Vec<Key>,
-    bidding_price: Vec<U256>,
+    // bids found in a record written before bids moved to `bid_book`, never serialized back
+    legacy_bids: Vec<(Key, U256)>,
 }
 
-impl ToBytes for SellingInMarket {
-    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
-        let mut result = bytesrepr::allocate_buffer(self)?;
-        result.extend(self.token_id.to_bytes()?);
-        result.extend(self.nft_contract.to_bytes()?);
-        result.extend(self.offeror.to_bytes()?);
-        result.extend(self.minimum_offer.to_bytes()?);
-        result.extend(self.is_active.to_bytes()?);
-        result.extend(self.bidder.to_bytes()?);
-        result.extend(self.bidding_price.to_bytes()?);
-        Ok(result)
+/// Layout of a version 1 `SellingInMarket` record: the version byte followed by
+/// `(token_id, nft_contract, offeror)` and `(minimum_offer, is_active)`. Tuples serialize as the
+/// plain concatenation of their fields, so this is also the `CLType` records are stored with.
+pub(crate) type SellingInMarketV1 = (u8, (String, Key, Option<Key>), (U256, bool));
+
+/// Recipients of the market fee with their share in basis points, the shares add up to
+/// `BPS_DIVISOR`.
+pub(crate) type FeeRecipients = Vec<(Key, U256)>;
+
+/// Fees in basis points overriding the market fee, by trade route.
+pub(crate) type FeeOverrides = Vec<(u8, U256)>;
+
+/// Returned by `get_market_config`: `(owner, market fee bps, fee recipients)`,
+/// `(paused flags, shut down, max bids per token)` and the market-wide route fee overrides.
+pub(crate) type MarketConfig = ((Key, U256, FeeRecipients), (u8, bool, u32), FeeOverrides);
+
+/// Returned by `get_collection_config`: `(NFT standard, paused flags)` with a standard of 0 when
+/// not supported, `(min bid, min bid increment, min bid increment bps)` and the collection fee
+/// overrides.
+pub(crate) type CollectionConfig = ((u8, u8), (U256, U256, U256), FeeOverrides);
+
+impl SellingInMarket {
+    fn market_key(&self) -> String {
+        get_token_market_key(&self.nft_contract, &self.token_id)
     }
 
-    fn serialized_length(&self) -> usize {
-        self.token_id.serialized_length()
-            + self.nft_contract.serialized_length()
-            + self.offeror.serialized_length()
-            + self.minimum_offer.serialized_length()
-            + self.is_active.serialized_length()
-            + self.bidder.serialized_length()
-            + self.bidding_price.serialized_length()
+    fn to_v1(&self) -> SellingInMarketV1 {
+        (
+            SELLING_IN_MARKET_VERSION,
+            (self.token_id.clone(), self.nft_contract, self.offeror),
+            (self.minimum_offer, self.is_active),
+        )
     }
-}
 
-impl FromBytes for SellingInMarket {
-    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+    // Records written before versioning have no tag and end with the bidder lists.
+    fn from_legacy_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
         let (token_id, remainder) = String::from_bytes(bytes)?;
         let (nft_contract, remainder) = Key::from_bytes(remainder)?;
         let (offeror, remainder) = Option::<Key>::from_bytes(remainder)?;
@@ -82,22 +98,59 @@ impl FromBytes for SellingInMarket {
         let (bidder, remainder) = Vec::<Key>::from_bytes(remainder)?;
         let (bidding_price, remainder) = Vec::<U256>::from_bytes(remainder)?;
 
+        if bidder.len() != bidding_price.len() {
+            return Err(bytesrepr::Error::Formatting);
+        }
+
         let ret = SellingInMarket {
             token_id,
             nft_contract,
             offeror,       //token seller
             minimum_offer, // min price in WCSPR
             is_active,
-            bidder,
-            bidding_price,
+            legacy_bids: bidder.into_iter().zip(bidding_price).collect(),
         };
         Ok((ret, remainder))
     }
 }
 
+impl ToBytes for SellingInMarket {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        self.to_v1().to_bytes()
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.to_v1().serialized_length()
+    }
+}
+
+impl FromBytes for SellingInMarket {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        // A legacy record starts with the u32 length of `token_id`. Read as a version byte and a
+        // `String`, that length picks up the first byte after it in its high byte, which never
+        // fits in a record, so legacy bytes always fall through to the legacy decoder.
+        if bytes.first() == Some(&SELLING_IN_MARKET_VERSION) {
+            if let Ok((record, remainder)) = SellingInMarketV1::from_bytes(bytes) {
+                let (_version, (token_id, nft_contract, offeror), (minimum_offer, is_active)) =
+                    record;
+                let ret = SellingInMarket {
+                    token_id,
+                    nft_contract,
+                    offeror,
+                    minimum_offer,
+                    is_active,
+                    legacy_bids: Vec::new(),
+                };
+                return Ok((ret, remainder));
+            }
+        }
+        SellingInMarket::from_legacy_bytes(bytes)
+    }
+}
+
 impl CLTyped for SellingInMarket {
     fn cl_type() -> CLType {
-        CLType::Any
+        SellingInMarketV1::cl_type()
     }
 }
 
@@ -126,23 +179,164 @@ pub extern "C" fn init() {
     );
 
     runtime::put_key(
-        MARKET_FEE_RECEIVER,
-        storage::new_uref(contract_fee_receiver).into(),
+        FEE_RECIPIENTS,
+        storage::new_uref(vec![(contract_fee_receiver, U256::from(BPS_DIVISOR))]).into(),
     );
 
-    runtime::put_key(MARKET_FEE, storage::new_uref(market_fee as U256).into());
+    if market_fee > U256::from(MAX_MARKET_FEE_BPS) {
+        runtime::revert(Error::FeeTooHigh);
+    }
+    runtime::put_key(MARKET_FEE_BPS, storage::new_uref(market_fee).into());
     runtime::put_key(
         TOKEN_CONTRACT_SUPPORT,
         storage::new_uref(nft_contract_hash).into(),
     );
 
-    storage::new_dictionary(SELLING_IN_MARKET)
-        .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    let package_access: URef = runtime::get_named_arg(ARG_PACKAGE_ACCESS);
+    set_key(PACKAGE_ACCESS_ADDR, package_access.addr());
+    set_key(ESCROWED_CEP78_TOKENS, 0u64);
+    create_dictionaries();
+    events::init();
+}
+
+// Runs right after `call()` added a new contract version. The purse, dictionaries and config keys
+// of the previous version are carried over by the host, this only adds what newer code expects.
+// CEP-47 tokens are escrowed under the package hash and stay movable. CEP-78 only lets the contract
+// hash holding a token move it, so the upgrade reverts while any CEP-78 token is escrowed and
+// sellers approve the new contract hash before listing again.
+#[no_mangle]
+pub extern "C" fn migrate() {
+    check_package_access();
+    let contract_version: u32 = runtime::get_named_arg(ARG_CONTRACT_VERSION);
+    check_escrow_released();
+
+    create_dictionaries();
+    events::init();
+    migrate_fee_receiver();
+    migrate_fee_to_bps();
+    set_key(CONTRACT_VERSION, contract_version);
+
+    events::emit(&MarketPlaceEvent::ContractUpgraded { contract_version });
+}
+
+// Only the holder of the package access URef can pass it, the host rejects forged URefs.
+fn check_package_access() {
+    let package_access: URef = runtime::get_named_arg(ARG_PACKAGE_ACCESS);
+    match get_key::<URefAddr>(PACKAGE_ACCESS_ADDR) {
+        Some(addr) if addr != package_access.addr() => runtime::revert(Error::InvalidPackageAccess),
+        Some(_) => {}
+        // versions before this one never recorded it, and `call()` has just added this version
+        // with it in the same deploy
+        None => set_key(PACKAGE_ACCESS_ADDR, package_access.addr()),
+    }
+}
+
+// Tokens escrowed under the contract hash of the previous version would be stranded once it is
+// disabled, so the previous version must have handed all of them back.
+fn check_escrow_released() {
+    match get_key::<u64>(ESCROWED_CEP78_TOKENS) {
+        Some(0) => {}
+        Some(_) => runtime::revert(Error::EscrowNotEmpty),
+        // versions before the count held CEP-78 tokens under their contract hash as well, only
+        // the upgrader can tell that none is left
+        None if NATIVE_NFT_STANDARD != NFT_STANDARD_CEP47 => {
+            let escrow_empty = helpers::get_named_arg_size(ARG_ESCROW_EMPTY).is_some()
+                && runtime::get_named_arg::<bool>(ARG_ESCROW_EMPTY);
+            if !escrow_empty {
+                runtime::revert(Error::EscrowNotEmpty);
+            }
+        }
+        None => {}
+    }
+    set_key(ESCROWED_CEP78_TOKENS, 0u64);
+}
+
+// Versions before the fee split paid the whole fee to `MARKET_FEE_RECEIVER`, it becomes the only
+// fee recipient.
+fn migrate_fee_receiver() {
+    if runtime::has_key(FEE_RECIPIENTS) {
+        return;
+    }
+    let fee_receiver: Key = helpers::get_stored_value_with_user_errors(
+        MARKET_FEE_RECEIVER,
+        Error::MissingFeeReceiver,
+        Error::InvalidFeeReceiver,
+    );
+    set_key(
+        FEE_RECIPIENTS,
+        vec![(fee_receiver, U256::from(BPS_DIVISOR))],
+    );
+    runtime::remove_key(MARKET_FEE_RECEIVER);
+}
+
+// Versions before basis points kept the market fee in thousandths under `MARKET_FEE`, a change
+// still queued is in the same unit.
+fn migrate_fee_to_bps() {
+    if runtime::has_key(MARKET_FEE_BPS) {
+        return;
+    }
+    let market_fee: U256 = helpers::get_stored_value_with_user_errors(
+        MARKET_FEE,
+        Error::MissingFeePortion,
+        Error::InvalidFeePortion,
+    );
+    set_key(MARKET_FEE_BPS, market_fee * U256::from(10u64));
+    runtime::remove_key(MARKET_FEE);
+    if let Some((new_fee, effective_at)) =
+        get_key::<Option<(U256, u64)>>(PENDING_MARKET_FEE).flatten()
+    {
+        set_key(
+            PENDING_MARKET_FEE,
+            Some((new_fee * U256::from(10u64), effective_at)),
+        );
+    }
+}
+
+// Every dictionary the contract works with, shared by `init` and `migrate`.
+fn create_dictionaries() {
+    for name in &[
+        SELLING_IN_MARKET,
+        BIDS,
+        BID_BOOK,
+        BID_LIST,
+        BID_LIST_LENGTH,
+        BID_POSITIONS,
+        MIN_BID,
+        MIN_BID_INCREMENT,
+        MIN_BID_INCREMENT_BPS,
+        NFT_SUPPPORTED_CONTRACTS,
+        COLLECTION_FEES,
+        MIGRATION_SOURCES,
+        MIGRATED_LISTINGS,
+        COLLECTION_PAUSED,
+        ROLES,
+        SELLING_LIST,
+        SELLING_LIST_LENGTH,
+        TOKEN_MARKET_LIST,
+        TOKEN_MARKET_LIST_LENGTH,
+        LISTING_INDEX_POSITIONS,
+        COLLECTION_STATS,
+        COLLECTION_FLOOR,
+        FLOOR_SCANS,
+        LAST_SALE,
+        TWAP_OBSERVATIONS,
+        TWAP_RING,
+        REFERRAL_TOTALS,
+        PENDING_FEE_OVERRIDES,
+    ] {
+        ensure_dictionary(name);
+    }
 }
 
 #[no_mangle]
 fn call() {
     let contract_name: String = runtime::get_named_arg(MARKET_CONTRACT_NAME);
+    let package_key_name = "mk_".to_string() + &contract_name + "_pk";
+    // A package installed earlier by this account gets a new version instead
+    if runtime::get_key(&package_key_name).is_some() {
+        upgrade(&contract_name, &package_key_name);
+        return;
+    }
     let contract_hash_key_name = contract_name.clone();
     let _contract_package_hash_key_name = contract_name.clone() + "_package_name";
     let contract_owner: Key = runtime::get_named_arg(ARG_CONTRACT_OWNER);
@@ -150,7 +344,7 @@ fn call() {
     let market_fee: U256 = runtime::get_named_arg(ARG_MARKET_FEE);
     let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
 
-    let (contract_package_hash, _access_uref) = storage::create_contract_package_at_hash();
+    let (contract_package_hash, access_uref) = storage::create_contract_package_at_hash();
 
     let named_keys: NamedKeys = named_keys::default(
         contract_owner,
@@ -165,13 +359,10 @@ fn call() {
         named_keys: NamedKeys,
     );
     runtime::put_key(contract_hash_key_name.as_str(), Key::from(contract_hash));
-    runtime::put_key(
-        &("mk_".to_string() + &contract_name + "_pk"),
-        Key::from(contract_package_hash),
-    );
+    runtime::put_key(&package_key_name, Key::from(contract_package_hash));
     runtime::put_key(
         &("mk_".to_string() + &contract_name + "_pk_access"),
-        Key::from(_access_uref),
+        Key::from(access_uref),
     );
 
     // set_key(PUNK_MARKETPLACE_KEY_NAME, Key::from(contract_hash));
@@ -184,17 +375,61 @@ fn call() {
             ARG_CONTRACT_OWNER => contract_owner,
             ARG_MARKET_FEE_RECEIVER => market_fee_receiver,
             ARG_MARKET_FEE => market_fee,
-            ARG_NFT_CONTRACT_HASH => nft_contract_hash
+            ARG_NFT_CONTRACT_HASH => nft_contract_hash,
+            ARG_PACKAGE_ACCESS => access_uref
         },
     );
 }
 
+// Adding a version takes the package access URef, and so does `migrate`. The installing account
+// keeps it under `mk_<name>_pk_access` and ownership transfers don't move it: that account keeps
+// running upgrades once the market changed hands, and an owner who wants to upgrade from their own
+// account installs a new package and moves the listings over with `migrate_listings`.
+fn upgrade(contract_name: &str, package_key_name: &str) {
+    let contract_package_hash: ContractPackageHash = runtime::get_key(package_key_name)
+        .and_then(Key::into_hash)
+        .map(ContractPackageHash::new)
+        .unwrap_or_revert_with(Error::MissingContractPackage);
+    let previous_contract_hash: ContractHash = runtime::get_key(contract_name)
+        .and_then(Key::into_hash)
+        .map(ContractHash::new)
+        .unwrap_or_revert_with(Error::MissingContractHash);
+    let package_access: URef = runtime::get_key(&(package_key_name.to_string() + "_access"))
+        .and_then(Key::into_uref)
+        .unwrap_or_revert_with(Error::MissingContractPackage);
+
+    let (contract_hash, contract_version) = storage::add_contract_version(
+        contract_package_hash,
+        entry_points::default(),
+        NamedKeys::new(),
+    );
+    runtime::put_key(contract_name, Key::from(contract_hash));
+
+    let mut migrate_args = runtime_args! {
+        ARG_CONTRACT_VERSION => contract_version,
+        ARG_PACKAGE_ACCESS => package_access
+    };
+    if helpers::get_named_arg_size(ARG_ESCROW_EMPTY).is_some() {
+        let escrow_empty: bool = runtime::get_named_arg(ARG_ESCROW_EMPTY);
+        migrate_args
+            .insert(ARG_ESCROW_EMPTY, escrow_empty)
+            .unwrap_or_revert();
+    }
+    runtime::call_contract::<()>(contract_hash, MIGRATE_ENTRY_POINT_NAME, migrate_args);
+
+    // The previous version shares the purse and dictionaries, it must not stay callable.
+    storage::disable_contract_version(contract_package_hash, previous_contract_hash)
+        .unwrap_or_revert();
+}
+
 #[no_mangle]
 pub extern "C" fn sell() {
     let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
     // Check if nft is supported or not
     check_enabled_nft(contract_hash: Key);
+    check_not_paused(contract_hash, PAUSE_LISTING);
     // Take token_id from runtime
+    check_not_migrating();
     let token_id: String = runtime::get_named_arg(ARG_TOKEN_ID);
     let seller: Key = helpers::get_immediate_caller_key();
 
@@ -209,11 +444,13 @@ pub extern "C" fn sell() {
 
 #[no_mangle]
 pub extern "C" fn change_price() {
+    let nft_contract_hash = get_optional_nft_contract_arg();
+    check_not_paused(nft_contract_hash, PAUSE_LISTING);
     let token_id: String = runtime::get_named_arg(ARG_TOKEN_ID);
     let new_price: U256 = runtime::get_named_arg(ARG_MINIMUM_OFFER);
+    let market_key = get_token_market_key(&nft_contract_hash, &token_id);
 
-    let token_market =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &token_id);
+    let token_market = get_selling_in_market(&market_key);
 
     let caller: Key = get_immediate_caller_key();
     let mut unwrap = token_market.unwrap();
@@ -226,12 +463,12 @@ pub extern "C" fn change_price() {
     if seller_key != caller {
         runtime::revert(Error::InvalidAccount)
     }
-    if unwrap.bidding_price.is_empty() && new_price <= *unwrap.bidding_price.last().unwrap() {
+    let top_bid = bid_book::top_bid(&market_key).filter(|&(_, price)| new_price <= price);
+    if let Some((bidder, bidding_price)) = top_bid {
+        // settles against the top bid, a trade like any other
+        check_not_paused(nft_contract_hash, PAUSE_BUYING);
         let contract_purse = helpers::get_uref(CONTRACT_PURSE);
 
-        let bidder = *unwrap.bidder.last().unwrap();
-        let bidding_price = *unwrap.bidding_price.last().unwrap();
-
         if bidding_price - new_price > U256::zero() {
             transfer_from_purse_to_account(
                 contract_purse,
@@ -240,6 +477,14 @@ pub extern "C" fn change_price() {
                 None,
             )
             .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+
+            events::emit(&MarketPlaceEvent::Refund {
+                nft_contract: unwrap.nft_contract,
+                token_id: unwrap.token_id.clone(),
+                recipient: bidder,
+                value: bidding_price - new_price,
+                reason: REFUND_PRICE_DIFFERENCE,
+            });
         }
 
         do_trade_change_price(
@@ -247,16 +492,17 @@ pub extern "C" fn change_price() {
             contract_purse,
             bidder,
             new_price,
-            get_self_key(),
+            escrow_key(unwrap.nft_contract),
         )
     } else {
         unwrap.minimum_offer = new_price;
-        write_dictionary_value_from_key(SELLING_IN_MARKET, &token_id, unwrap.clone());
-        events::emit(&MarketPlaceEvent::Sell {
+        write_dictionary_value_from_key(SELLING_IN_MARKET, &market_key, unwrap.clone());
+        market_stats::listing_priced(unwrap.nft_contract, &unwrap.token_id, new_price);
+        events::emit(&MarketPlaceEvent::ChangePrice {
             nft_contract: unwrap.nft_contract,
             token_id: unwrap.token_id.to_string(),
             offeror: unwrap.offeror.unwrap(),
-            minimum_offer: new_price,
+            new_price,
             is_active: true,
         });
     }
@@ -264,11 +510,13 @@ pub extern "C" fn change_price() {
 
 #[no_mangle]
 pub extern "C" fn accept_price() {
+    let nft_contract_hash = get_optional_nft_contract_arg();
+    check_not_paused(nft_contract_hash, PAUSE_BUYING);
     let token_id: String = runtime::get_named_arg(ARG_TOKEN_ID);
     let accepted_price: U256 = runtime::get_named_arg(ARG_ACCEPTED_PRICE);
     let accepted_bidder: Key = runtime::get_named_arg(ARG_ACCEPTED_BIDDER);
-    let token_market =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &token_id);
+    let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+    let token_market = get_selling_in_market(&market_key);
 
     let caller: Key = get_immediate_caller_key();
     let mut unwrap = token_market.unwrap();
@@ -278,20 +526,21 @@ pub extern "C" fn accept_price() {
         runtime::revert(Error::InvalidAccount)
     }
     let contract_purse = helpers::get_uref(CONTRACT_PURSE);
-    let old_index = unwrap
-        .bidder
-        .iter()
-        .position(|x| x.into_account().unwrap() == accepted_bidder.into_account().unwrap());
 
-    if unwrap.bidding_price[old_index.unwrap()] != accepted_price {
+    if bid_book::bid_of(&market_key, accepted_bidder) != Some(accepted_price) {
         runtime::revert(Error::InvalidContext);
     }
 
-    // remove this bidder from bidder list
-
-    unwrap.bidder.remove(old_index.unwrap());
-    unwrap.bidding_price.remove(old_index.unwrap());
-    do_trade(&mut unwrap, contract_purse, accepted_bidder, accepted_price)
+    // remove this bidder from the bid book
+    bid_book::remove_bid(&market_key, accepted_bidder);
+    do_trade(
+        &mut unwrap,
+        contract_purse,
+        accepted_bidder,
+        accepted_price,
+        TRADE_ROUTE_ACCEPTED_BID,
+        None,
+    )
 }
 
 #[no_mangle]
@@ -304,6 +553,8 @@ pub extern "C" fn buy() {
     .unwrap_or_revert_with(Error::CanNotGetNFTContract);
     // Check if nft is supported or not
     check_enabled_nft(nft_contract_hash: Key);
+    check_not_paused(nft_contract_hash, PAUSE_BUYING);
+    check_deadline();
     let token_id = helpers::get_named_arg_with_user_errors::<String>(
         ARG_TOKEN_ID,
         Error::MissingTokenID,
@@ -318,8 +569,8 @@ pub extern "C" fn buy() {
     )
     .unwrap_or_revert_with(Error::CanNotGetBuyer);
 
-    let mut unwrap =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &token_id).unwrap();
+    let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+    let mut unwrap = get_selling_in_market(&market_key).unwrap();
 
     if unwrap.nft_contract != nft_contract_hash || unwrap.token_id != token_id {
         runtime::revert(Error::InvalidInputTokenInfo);
@@ -330,6 +581,7 @@ pub extern "C" fn buy() {
     }
 
     let needed_amount: U256 = unwrap.minimum_offer;
+    check_max_price(needed_amount);
 
     let allowed_cspr_amount = helpers::get_named_arg_with_user_errors::<U256>(
         AMOUNT_RUNTIME_ARG_NAME,
@@ -352,24 +604,32 @@ pub extern "C" fn buy() {
         .unwrap_or_revert_with(Error::CanNotTransferCSPR);
 
     // check whether the buyer has a bid for this nft, if yes, refund it
-    let buyer_index = unwrap
-        .bidder
-        .iter()
-        .position(|x| x.into_account().unwrap() == buyer.into_account().unwrap());
-    if let Some(..) = buyer_index {
-        let buyer_index = buyer_index.unwrap();
+    if let Some(buyer_bid) = bid_book::remove_bid(&market_key, buyer) {
         transfer_from_purse_to_account(
             contract_purse,
-            unwrap.bidder[buyer_index].into_account().unwrap(),
-            u256_to_u512(unwrap.bidding_price[buyer_index]),
+            buyer.into_account().unwrap(),
+            u256_to_u512(buyer_bid),
             None,
         )
         .unwrap_or_revert_with(Error::CanNotTransferCSPR);
-        unwrap.bidder.remove(buyer_index);
-        unwrap.bidding_price.remove(buyer_index);
+
+        events::emit(&MarketPlaceEvent::Refund {
+            nft_contract: nft_contract_hash,
+            token_id: token_id.clone(),
+            recipient: buyer,
+            value: buyer_bid,
+            reason: REFUND_BID_ON_BUY,
+        });
     }
 
-    do_trade(&mut unwrap, contract_purse, buyer, needed_amount);
+    do_trade(
+        &mut unwrap,
+        contract_purse,
+        buyer,
+        needed_amount,
+        TRADE_ROUTE_BUY,
+        get_referrer_arg(buyer),
+    );
 }
 
 #[no_mangle]
@@ -383,6 +643,8 @@ pub extern "C" fn bid() {
 
     // Check if nft is supported or not
     check_enabled_nft(nft_contract_hash: Key);
+    check_not_paused(nft_contract_hash, PAUSE_BIDDING);
+    check_deadline();
     let token_id = helpers::get_named_arg_with_user_errors::<String>(
         ARG_TOKEN_ID,
         Error::MissingTokenID,
@@ -404,12 +666,10 @@ pub extern "C" fn bid() {
     )
     .unwrap_or_revert_with(Error::CanNotGetBuyer);
 
-    // Bid must be higher than 100 cspr
-    if bidding_price < U256::from(100000000000u64) {
-        runtime::revert(Error::InvalidAmount)
-    }
-    let token_market =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &token_id);
+    check_not_migrating();
+    let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+    let token_market = get_selling_in_market(&market_key);
+    let is_new_market = token_market.is_none();
 
     let mut unwrap = if let Some(..) = token_market {
         token_market.unwrap()
@@ -420,8 +680,7 @@ pub extern "C" fn bid() {
             nft_contract: nft_contract_hash,
             minimum_offer: 0.into(),
             is_active: false,
-            bidder: Vec::new(),
-            bidding_price: Vec::new(),
+            legacy_bids: Vec::new(),
         }
     };
 
@@ -430,10 +689,12 @@ pub extern "C" fn bid() {
     }
 
     // check if bidder is already make bid
-    if unwrap.bidder.contains(&bidder) {
+    if bid_book::bid_of(&market_key, bidder).is_some() {
         runtime::revert(Error::InvalidContext);
     }
 
+    check_bid_amount(&unwrap, bidder, bidding_price);
+
     // Check if bidding price is higher than minimum_offer
 
     let src_purse: URef = helpers::get_named_arg_with_user_errors::<URef>(
@@ -446,21 +707,35 @@ pub extern "C" fn bid() {
     let needed_amount: U256 = unwrap.minimum_offer;
 
     if unwrap.is_active && bidding_price >= needed_amount {
+        // the bid buys the token
+        check_not_paused(nft_contract_hash, PAUSE_BUYING);
+        check_max_price(needed_amount);
         let contract_purse = helpers::get_uref(CONTRACT_PURSE);
 
         transfer_from_purse_to_purse(src_purse, contract_purse, u256_to_u512(needed_amount), None)
             .unwrap_or_revert_with(Error::CanNotTransferCSPR);
-        do_trade(&mut unwrap, contract_purse, bidder, needed_amount);
+        do_trade(
+            &mut unwrap,
+            contract_purse,
+            bidder,
+            needed_amount,
+            TRADE_ROUTE_BID,
+            get_referrer_arg(bidder),
+        );
     } else {
         let contract_purse = helpers::get_uref(CONTRACT_PURSE);
 
         transfer_from_purse_to_purse(src_purse, contract_purse, u256_to_u512(bidding_price), None)
             .unwrap_or_revert_with(Error::CanNotTransferCSPR);
 
-        // save to selling_in_market
-        insert_new_bidder(&mut unwrap, bidder, bidding_price);
+        // save to the bid book, refunding the lowest bid if it had to make room
+        if let Some(evicted) = bid_book::add_bid(&market_key, bidder, bidding_price) {
+            refund_evicted_bid(contract_purse, &unwrap, evicted);
+        }
 
-        write_dictionary_value_from_key(SELLING_IN_MARKET, &token_id, unwrap.clone());
+        if is_new_market {
+            write_dictionary_value_from_key(SELLING_IN_MARKET, &market_key, unwrap.clone());
+        }
 
         events::emit(&MarketPlaceEvent::Bid {
             nft_contract: nft_contract_hash,
@@ -490,8 +765,8 @@ pub extern "C" fn revoke_bid() {
     )
     .unwrap_or_revert_with(Error::CanNotGetTokenId);
 
-    let mut unwrap =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &token_id).unwrap();
+    let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+    let unwrap = get_selling_in_market(&market_key).unwrap();
 
     if unwrap.nft_contract != nft_contract_hash || unwrap.token_id != token_id {
         runtime::revert(Error::InvalidInputTokenInfo);
@@ -499,41 +774,35 @@ pub extern "C" fn revoke_bid() {
 
     let caller: Key = get_immediate_caller_key();
 
-    let removed_index = unwrap
-        .bidder
-        .iter()
-        .position(|x| x.into_account().unwrap() == caller.into_account().unwrap());
-
-    let s_index = match removed_index {
-        Some(index) => index,
+    let ret_value_event = match bid_book::remove_bid(&market_key, caller) {
+        Some(bidding_price) => bidding_price,
         None => runtime::revert(Error::InvalidBidder),
     };
 
     let contract_purse = helpers::get_uref(CONTRACT_PURSE);
-    // Check if bidding price is higher than minimum_offer
     transfer_from_purse_to_account(
         contract_purse,
-        unwrap.bidder[s_index].into_account().unwrap(),
-        u256_to_u512(unwrap.bidding_price[s_index]),
+        caller.into_account().unwrap(),
+        u256_to_u512(ret_value_event),
         None,
     )
     .unwrap_or_revert_with(Error::CanNotTransferCSPR);
 
-    let ret_value_event = unwrap.bidding_price[s_index];
-    // remove bidder and bidding_price from array
-    unwrap.bidder.remove(s_index);
-    unwrap.bidding_price.remove(s_index);
-
-    write_dictionary_value_from_key(SELLING_IN_MARKET, &token_id, unwrap.clone());
-
     events::emit(&MarketPlaceEvent::RevokeBid {
         nft_contract: nft_contract_hash,
-        token_id,
+        token_id: token_id.clone(),
         offeror: unwrap.offeror.unwrap_or_else(null_key),
         bidder: caller,
         value: ret_value_event,
         is_revoke: true,
     });
+    events::emit(&MarketPlaceEvent::Refund {
+        nft_contract: nft_contract_hash,
+        token_id,
+        recipient: caller,
+        value: ret_value_event,
+        reason: REFUND_BID_REVOKED,
+    });
 }
 
 #[no_mangle]
@@ -547,6 +816,8 @@ pub extern "C" fn increase_bid() {
 
     // Check if nft is supported or not
     check_enabled_nft(nft_contract_hash: Key);
+    check_not_paused(nft_contract_hash, PAUSE_BIDDING);
+    check_deadline();
     let token_id = helpers::get_named_arg_with_user_errors::<String>(
         ARG_TOKEN_ID,
         Error::MissingTokenID,
@@ -568,8 +839,9 @@ pub extern "C" fn increase_bid() {
     )
     .unwrap_or_revert_with(Error::CanNotGetBuyer);
 
-    let token_market =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &token_id);
+    check_not_migrating();
+    let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+    let token_market = get_selling_in_market(&market_key);
 
     // print(&added_price.to_string());
     let mut unwrap = token_market.unwrap();
@@ -587,27 +859,23 @@ pub extern "C" fn increase_bid() {
     )
     .unwrap_or_revert_with(Error::CanNotGetUserPurse);
 
-    // check if bidder is already made bid
-    if !unwrap.bidder.contains(&bidder) {
-        runtime::revert(Error::InvalidContext);
-    }
-
-    // get old bid of this bidder
-
-    let old_index = unwrap
-        .bidder
-        .iter()
-        .position(|x| x.into_account().unwrap() == bidder.into_account().unwrap());
-
-    let old_bidding_price = unwrap.bidding_price[old_index.unwrap()];
+    // check if bidder is already made bid and get the old bid of this bidder
+    let old_bidding_price = match bid_book::bid_of(&market_key, bidder) {
+        Some(bidding_price) => bidding_price,
+        None => runtime::revert(Error::InvalidContext),
+    };
 
     // Check if increased bidding price is higher than minimum_offer
 
     let increased_bidding_price = old_bidding_price + added_price;
 
+    check_bid_amount(&unwrap, bidder, increased_bidding_price);
+
     let needed_amount: U256 = unwrap.minimum_offer;
 
     if increased_bidding_price >= needed_amount && unwrap.is_active {
+        check_not_paused(nft_contract_hash, PAUSE_BUYING);
+        check_max_price(needed_amount);
         // take cspr from bidder to contract
         transfer_from_purse_to_purse(
             src_purse,
@@ -617,24 +885,21 @@ pub extern "C" fn increase_bid() {
         )
         .unwrap_or_revert_with(Error::CanNotTransferCSPR);
 
-        unwrap.bidder.remove(old_index.unwrap());
-        unwrap.bidding_price.remove(old_index.unwrap());
-        do_trade(&mut unwrap, contract_purse, bidder, needed_amount);
+        bid_book::remove_bid(&market_key, bidder);
+        do_trade(
+            &mut unwrap,
+            contract_purse,
+            bidder,
+            needed_amount,
+            TRADE_ROUTE_BID,
+            get_referrer_arg(bidder),
+        );
     } else {
-        if unwrap.bidder.len() != unwrap.bidding_price.len() {
-            runtime::revert(Error::InvalidContext);
-        }
-
         transfer_from_purse_to_purse(src_purse, contract_purse, u256_to_u512(added_price), None)
             .unwrap_or_revert_with(Error::CanNotTransferCSPR);
 
-        unwrap.bidder.remove(old_index.unwrap() as usize);
-        unwrap.bidding_price.remove(old_index.unwrap() as usize);
-
-        // save to selling_in_market
-        insert_new_bidder(&mut unwrap, bidder: Key, increased_bidding_price);
-
-        write_dictionary_value_from_key(SELLING_IN_MARKET, &token_id, unwrap.clone());
+        // save to the bid book
+        bid_book::raise_bid(&market_key, bidder, increased_bidding_price);
 
         events::emit(&MarketPlaceEvent::Bid {
             nft_contract: nft_contract_hash,
@@ -654,8 +919,8 @@ pub extern "C" fn revoke_sell() {
 
     let token_id: String = runtime::get_named_arg(ARG_TOKEN_ID);
 
-    let mut token_market =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &token_id).unwrap();
+    let market_key = get_token_market_key(&contract_hash, &token_id);
+    let mut token_market = get_selling_in_market(&market_key).unwrap();
 
     let caller = get_immediate_caller_key();
     if token_market.offeror.unwrap() != caller {
@@ -669,9 +934,11 @@ pub extern "C" fn revoke_sell() {
     token_market.offeror = None;
     // token_market.offeror;
     // When revoke-offer => token_market will be set is_active to false
-    write_dictionary_value_from_key(SELLING_IN_MARKET, &token_id, token_market.clone());
+    write_dictionary_value_from_key(SELLING_IN_MARKET, &market_key, token_market.clone());
+    listing_index::remove(contract_hash, &token_id);
+    market_stats::listing_removed(contract_hash, &token_id);
 
-    cep78_transfer_from(&contract_hash, get_self_key(), caller, token_id);
+    nft_transfer_from(&contract_hash, escrow_key(contract_hash), caller, token_id);
 
     events::emit(&MarketPlaceEvent::Revoke {
         nft_contract: contract_hash,
@@ -682,200 +949,1251 @@ pub extern "C" fn revoke_sell() {
     });
 }
 
-// Disable - do nothing
 #[no_mangle]
 pub extern "C" fn set_support_token() {
-    // let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
-
-    // let nft_contract_str_key = helpers::make_dictionary_item_key_for_key(nft_contract_hash);
-
-    // // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
 
-    // let caller = get_immediate_caller_key();
+    let nft_contract_str_key = helpers::make_dictionary_item_key_for_key(nft_contract_hash);
 
-    // // let caller = helpers::get_verified_caller().unwrap_or_revert();
-    // let current_contract_owner = helpers::get_stored_value_with_user_errors(
-    //     CONTRACT_OWNER_KEY_NAME,
-    //     Error::MissingContractOwner,
-    //     Error::InvalidContractOwner,
-    // );
+    roles::require(ROLE_CURATOR);
+    let mut token_list = get_key::<Vec<String>>(TOKEN_CONTRACT_LIST).unwrap_or_default();
 
-    // if caller != current_contract_owner {
-    //     runtime::revert(Error::InvalidContractOwner);
-    // }
-    // // Change from Vec<Key> to Vec<String>
-    // let mut token_list = get_key::<Vec<String>>(TOKEN_CONTRACT_LIST).unwrap_or_revert();
+    let nft_enabled: bool = runtime::get_named_arg(ARG_NFT_ENABLED);
+    if nft_enabled {
+        let nft_standard: u8 = runtime::get_named_arg(ARG_NFT_STANDARD);
+        if nft_standard != NFT_STANDARD_CEP47 && nft_standard != NFT_STANDARD_CEP78 {
+            runtime::revert(Error::InvalidNFTStandard);
+        }
+        if !token_list.contains(&nft_contract_hash.to_string()) {
+            token_list.push(nft_contract_hash.to_string());
+            set_key(TOKEN_CONTRACT_LIST, token_list);
+        }
 
-    // let nft_enabled: bool = runtime::get_named_arg(ARG_NFT_ENABLED);
-    // if nft_enabled {
-    //     if !token_list.contains(&nft_contract_hash.to_string()) {
-    //         token_list.push(nft_contract_hash.to_string());
-    //         set_key(TOKEN_CONTRACT_LIST, token_list);
-    //     }
+        write_dictionary_value_from_key(
+            NFT_SUPPPORTED_CONTRACTS,
+            &nft_contract_str_key,
+            nft_standard,
+        );
 
-    //     write_dictionary_value_from_key(TOKEN_CONTRACT_MAP, &nft_contract_str_key, true);
-    // } else {
-    //     if token_list.contains(&nft_contract_hash.to_string()) {
-    //         token_list.retain(|x| *x != nft_contract_hash.to_string());
-    //         set_key(TOKEN_CONTRACT_LIST, token_list);
-    //     }
-    //     write_dictionary_value_from_key(TOKEN_CONTRACT_MAP, &nft_contract_str_key, false);
+        events::emit(&MarketPlaceEvent::CollectionUpdated {
+            nft_contract: nft_contract_hash,
+            nft_standard,
+        });
+    } else {
+        if token_list.contains(&nft_contract_hash.to_string()) {
+            token_list.retain(|x| *x != nft_contract_hash.to_string());
+            set_key(TOKEN_CONTRACT_LIST, token_list);
+        }
+        write_dictionary_value_from_key(NFT_SUPPPORTED_CONTRACTS, &nft_contract_str_key, 0u8);
 
-    //     // write_dictionary_value_from_key(TOKEN_CONTRACT_MAP, &nft_contract_hash.to_string(), false);
-    // }
+        events::emit(&MarketPlaceEvent::CollectionUpdated {
+            nft_contract: nft_contract_hash,
+            nft_standard: 0,
+        });
+    }
 }
 
+// Kept for existing tooling, only proposes the new owner like `propose_owner`.
 #[no_mangle]
 pub extern "C" fn transfer_owner() {
+    propose_owner();
+}
+
+// The proposed owner only takes over once it calls `accept_ownership`. Only the owner can
+// propose, an admin could otherwise hand ownership to itself.
+#[no_mangle]
+pub extern "C" fn propose_owner() {
     let new_contract_owner: Key = runtime::get_named_arg(ARG_CONTRACT_OWNER);
-    // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
-    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+    let current_contract_owner = roles::require_owner();
+    set_key(PENDING_CONTRACT_OWNER, Some(new_contract_owner));
+
+    events::emit(&MarketPlaceEvent::OwnershipTransferStarted {
+        previous_owner: current_contract_owner,
+        new_owner: new_contract_owner,
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn accept_ownership() {
+    let pending_contract_owner = get_key::<Option<Key>>(PENDING_CONTRACT_OWNER)
+        .flatten()
+        .unwrap_or_revert_with(Error::NoPendingOwner);
+    if get_immediate_caller_key() != pending_contract_owner {
+        runtime::revert(Error::CallerNotPendingOwner);
+    }
+    let previous_contract_owner: Key = helpers::get_stored_value_with_user_errors(
         CONTRACT_OWNER_KEY_NAME,
         Error::MissingContractOwner,
         Error::InvalidContractOwner,
     );
+    set_key(CONTRACT_OWNER_KEY_NAME, pending_contract_owner);
+    set_key(PENDING_CONTRACT_OWNER, None::<Key>);
 
-    let caller = get_immediate_caller_key();
+    events::emit(&MarketPlaceEvent::OwnershipTransferred {
+        previous_owner: previous_contract_owner,
+        new_owner: pending_contract_owner,
+    });
+}
 
-    if caller != current_contract_owner {
-        runtime::revert(Error::InvalidContractOwner);
-    }
-    set_key(CONTRACT_OWNER_KEY_NAME, new_contract_owner);
+#[no_mangle]
+pub extern "C" fn cancel_ownership_transfer() {
+    let caller = roles::require_owner();
+    let pending_contract_owner = get_key::<Option<Key>>(PENDING_CONTRACT_OWNER)
+        .flatten()
+        .unwrap_or_revert_with(Error::NoPendingOwner);
+    set_key(PENDING_CONTRACT_OWNER, None::<Key>);
+
+    events::emit(&MarketPlaceEvent::OwnershipTransferCancelled {
+        pending_owner: pending_contract_owner,
+        sender: caller,
+    });
 }
 
+// Shorthand for `set_fee_recipients` with a single recipient taking the whole fee.
 #[no_mangle]
 pub extern "C" fn set_fee_receiver() {
     let fee_receiver: Key = runtime::get_named_arg(ARG_MARKET_FEE_RECEIVER);
-    // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
-    let current_contract_owner = helpers::get_stored_value_with_user_errors(
-        CONTRACT_OWNER_KEY_NAME,
-        Error::MissingContractOwner,
-        Error::InvalidContractOwner,
+    roles::require(ROLE_FEE_MANAGER);
+    schedule_fee_recipients(vec![(fee_receiver, U256::from(BPS_DIVISOR))]);
+}
+
+// Queues the change, see `execute_config_change`.
+#[no_mangle]
+pub extern "C" fn set_fee_recipients() {
+    let fee_recipients: FeeRecipients = runtime::get_named_arg(ARG_FEE_RECIPIENTS);
+    roles::require(ROLE_FEE_MANAGER);
+    schedule_fee_recipients(fee_recipients);
+}
+
+fn schedule_fee_recipients(fee_recipients: FeeRecipients) {
+    check_fee_recipients(&fee_recipients);
+    let effective_at = get_effective_time_arg();
+    let value = fee_recipients_to_string(&fee_recipients);
+    cancel_replaced_change(
+        CONFIG_FEE_RECIPIENTS,
+        PENDING_FEE_RECIPIENTS,
+        |fee_recipients: &FeeRecipients| fee_recipients_to_string(fee_recipients),
     );
-    let caller = get_immediate_caller_key();
+    set_key(PENDING_FEE_RECIPIENTS, Some((fee_recipients, effective_at)));
+
+    events::emit(&MarketPlaceEvent::ConfigChangeScheduled {
+        config: CONFIG_FEE_RECIPIENTS,
+        value,
+        effective_at,
+    });
+}
 
-    // let caller = helpers::get_verified_caller().unwrap_or_revert();
-    if caller != current_contract_owner {
-        runtime::revert(Error::InvalidContractOwner);
+// Recipients must be distinct accounts with a non-zero share, and the shares must add up to 100%.
+fn check_fee_recipients(fee_recipients: &[(Key, U256)]) {
+    if fee_recipients.is_empty() || fee_recipients.len() > MAX_FEE_RECIPIENTS {
+        runtime::revert(Error::InvalidFeeRecipients);
+    }
+    let mut total_share = U256::zero();
+    for (i, &(recipient, share)) in fee_recipients.iter().enumerate() {
+        if recipient.into_account().is_none()
+            || share.is_zero()
+            || fee_recipients[..i]
+                .iter()
+                .any(|&(other, _)| other == recipient)
+        {
+            runtime::revert(Error::InvalidFeeRecipients);
+        }
+        total_share += share;
     }
-    set_key(MARKET_FEE_RECEIVER, fee_receiver);
+    if total_share != U256::from(BPS_DIVISOR) {
+        runtime::revert(Error::InvalidFeeRecipients);
+    }
+}
+
+// `recipient:share` pairs separated by commas, as carried by the config change events.
+fn fee_recipients_to_string(fee_recipients: &[(Key, U256)]) -> String {
+    fee_recipients
+        .iter()
+        .map(|(recipient, share)| recipient.to_string() + ":" + &share.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
 }
 
+// One-way switch: trading stops for good and escrow can only go back to its owners through
+// `emergency_withdraw_nfts` and `emergency_withdraw_cspr`.
 #[no_mangle]
-pub extern "C" fn emergency_withdraw_cspr() {
-    let amount: U256 = runtime::get_named_arg(AMOUNT_RUNTIME_ARG_NAME);
-    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
-        CONTRACT_OWNER_KEY_NAME,
-        Error::MissingContractOwner,
-        Error::InvalidContractOwner,
-    );
-    let caller = get_immediate_caller_key();
+pub extern "C" fn emergency_shutdown() {
+    let caller = roles::require(ROLE_ADMIN);
+    check_not_shut_down();
+    set_key(EMERGENCY_SHUTDOWN, true);
+
+    events::emit(&MarketPlaceEvent::EmergencyShutdown { sender: caller });
+}
 
-    if caller != current_contract_owner {
-        runtime::revert(Error::InvalidContractOwner);
+// After a shutdown anyone can refund the bids on the given tokens, always to their bidders.
+#[no_mangle]
+pub extern "C" fn emergency_withdraw_cspr() {
+    let token_ids: Vec<String> = runtime::get_named_arg(ARG_TOKEN_IDS);
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    if !is_shut_down() {
+        runtime::revert(Error::MarketNotShutdown);
     }
     let contract_purse = helpers::get_uref(CONTRACT_PURSE);
 
-    transfer_from_purse_to_account(
-        contract_purse,
-        caller.into_account().unwrap(),
-        u256_to_u512(amount),
-        None,
-    )
-    .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+    for token_id in token_ids {
+        let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+        // moves bids still kept in a legacy record into the bid book
+        get_selling_in_market(&market_key);
+
+        for (bidder, bidding_price) in bid_book::clear_bids(&market_key) {
+            transfer_from_purse_to_account(
+                contract_purse,
+                bidder.into_account().unwrap(),
+                u256_to_u512(bidding_price),
+                None,
+            )
+            .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+
+            events::emit(&MarketPlaceEvent::EmergencyBidRefunded {
+                nft_contract: nft_contract_hash,
+                token_id: token_id.clone(),
+                bidder,
+                value: bidding_price,
+            });
+        }
+    }
 }
 
+// After a shutdown anyone can return escrowed tokens, always to the seller that listed them.
 #[no_mangle]
 pub extern "C" fn emergency_withdraw_nfts() {
     let token_ids: Vec<String> = runtime::get_named_arg(ARG_TOKEN_IDS);
     let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
-    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
-        CONTRACT_OWNER_KEY_NAME,
-        Error::MissingContractOwner,
-        Error::InvalidContractOwner,
-    );
-    let caller = get_immediate_caller_key();
-
-    if caller != current_contract_owner {
-        runtime::revert(Error::InvalidContractOwner);
+    if !is_shut_down() {
+        runtime::revert(Error::MarketNotShutdown);
     }
-    for token_id in &token_ids {
-        cep78_transfer_from(
+
+    for token_id in token_ids {
+        let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+        let mut token_market = match get_selling_in_market(&market_key) {
+            Some(token_market) if token_market.is_active => token_market,
+            _ => continue,
+        };
+        let offeror = token_market.offeror.unwrap();
+        token_market.is_active = false;
+        token_market.offeror = None;
+        write_dictionary_value_from_key(SELLING_IN_MARKET, &market_key, token_market);
+        listing_index::remove(nft_contract_hash, &token_id);
+        market_stats::listing_removed(nft_contract_hash, &token_id);
+
+        nft_transfer_from(
             &nft_contract_hash,
-            get_self_key(),
-            current_contract_owner,
+            escrow_key(nft_contract_hash),
+            offeror,
             token_id.clone(),
         );
+
+        events::emit(&MarketPlaceEvent::EmergencyNftReturned {
+            nft_contract: nft_contract_hash,
+            token_id,
+            offeror,
+        });
     }
 }
 
+// Queues the change, see `execute_config_change`. The fee is in basis points of the trade price.
 #[no_mangle]
 pub extern "C" fn change_fee() {
-    // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
-    let caller = get_immediate_caller_key();
-    let current_contract_owner = helpers::get_stored_value_with_user_errors(
-        CONTRACT_OWNER_KEY_NAME,
-        Error::MissingContractOwner,
-        Error::InvalidContractOwner,
-    );
-
-    // let caller = helpers::get_verified_caller().unwrap_or_revert();
-    if caller != current_contract_owner {
-        runtime::revert(Error::InvalidContractOwner);
-    }
-    let new_fee: U256 = runtime::get_named_arg(MARKET_FEE);
-    if new_fee > U256::from(200u64) {
+    roles::require(ROLE_FEE_MANAGER);
+    let new_fee: U256 = runtime::get_named_arg(ARG_MARKET_FEE);
+    if new_fee > U256::from(MAX_MARKET_FEE_BPS) {
         runtime::revert(Error::FeeTooHigh);
     }
-    set_key(MARKET_FEE, new_fee);
+    let effective_at = get_effective_time_arg();
+    cancel_replaced_change(CONFIG_MARKET_FEE, PENDING_MARKET_FEE, |fee: &U256| {
+        fee.to_string()
+    });
+    set_key(PENDING_MARKET_FEE, Some((new_fee, effective_at)));
+
+    events::emit(&MarketPlaceEvent::ConfigChangeScheduled {
+        config: CONFIG_MARKET_FEE,
+        value: new_fee.to_string(),
+        effective_at,
+    });
 }
 
-fn set_selling(contract_hash: &Key, token_identifier: &str, offeror: Key, minimum_offer: U256) {
-    // runtime::print("set selling");
-    let exists =
-        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, token_identifier);
-    let mut token_market = if let Some(..) = exists {
-        exists.unwrap()
-    } else {
-        SellingInMarket {
-            offeror: Some(offeror),
-            token_id: token_identifier.to_string(),
-            nft_contract: *contract_hash,
-            minimum_offer,
-            is_active: true,
-            bidder: Vec::new(),
-            bidding_price: Vec::new(),
+// Applies a queued configuration change once its effective time has passed. Anyone can
+// call it, the change was public since it was scheduled.
+#[no_mangle]
+pub extern "C" fn execute_config_change() {
+    let config: u8 = runtime::get_named_arg(ARG_CONFIG);
+    let now = u64::from(runtime::get_blocktime());
+
+    let value = match config {
+        CONFIG_MARKET_FEE => {
+            let (new_fee, effective_at) = get_key::<Option<(U256, u64)>>(PENDING_MARKET_FEE)
+                .flatten()
+                .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            if now < effective_at {
+                runtime::revert(Error::ConfigChangeNotDue);
+            }
+            set_key(MARKET_FEE_BPS, new_fee);
+            set_key(PENDING_MARKET_FEE, None::<(U256, u64)>);
+            new_fee.to_string()
+        }
+        CONFIG_MARKET_FEE_RECEIVER => {
+            let (fee_receiver, effective_at) =
+                get_key::<Option<(Key, u64)>>(PENDING_MARKET_FEE_RECEIVER)
+                    .flatten()
+                    .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            if now < effective_at {
+                runtime::revert(Error::ConfigChangeNotDue);
+            }
+            // Only queued by versions before the fee split.
+            set_key(
+                FEE_RECIPIENTS,
+                vec![(fee_receiver, U256::from(BPS_DIVISOR))],
+            );
+            set_key(PENDING_MARKET_FEE_RECEIVER, None::<(Key, u64)>);
+            fee_receiver.to_string()
         }
+        CONFIG_FEE_RECIPIENTS => {
+            let (fee_recipients, effective_at) =
+                get_key::<Option<(FeeRecipients, u64)>>(PENDING_FEE_RECIPIENTS)
+                    .flatten()
+                    .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            if now < effective_at {
+                runtime::revert(Error::ConfigChangeNotDue);
+            }
+            let value = fee_recipients_to_string(&fee_recipients);
+            set_key(FEE_RECIPIENTS, fee_recipients);
+            set_key(PENDING_FEE_RECIPIENTS, None::<(FeeRecipients, u64)>);
+            value
+        }
+        CONFIG_MIGRATION_TARGET => {
+            let (target_marketplace, effective_at) =
+                get_key::<Option<(Key, u64)>>(PENDING_MIGRATION_TARGET)
+                    .flatten()
+                    .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            if now < effective_at {
+                runtime::revert(Error::ConfigChangeNotDue);
+            }
+            // a migration in progress keeps its target until it is cleared
+            if let Some(migration_target) = get_key::<Key>(MIGRATION_TARGET) {
+                if migration_target != target_marketplace {
+                    runtime::revert(Error::MigrationTargetMismatch);
+                }
+            }
+            set_key(APPROVED_MIGRATION_TARGET, target_marketplace);
+            set_key(PENDING_MIGRATION_TARGET, None::<(Key, u64)>);
+            target_marketplace.to_string()
+        }
+        CONFIG_FEE_OVERRIDE => {
+            let pending_key = get_pending_fee_override_key_arg();
+            let (change, effective_at) = get_dictionary_value_from_key::<
+                Option<(FeeOverrideChange, u64)>,
+            >(PENDING_FEE_OVERRIDES, &pending_key)
+            .flatten()
+            .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            if now < effective_at {
+                runtime::revert(Error::ConfigChangeNotDue);
+            }
+            write_fee_override(change);
+            write_dictionary_value_from_key(
+                PENDING_FEE_OVERRIDES,
+                &pending_key,
+                None::<(FeeOverrideChange, u64)>,
+            );
+            fee_override_to_string(change)
+        }
+        _ => runtime::revert(Error::InvalidConfig),
     };
 
-    token_market.offeror = Some(offeror);
-    token_market.is_active = true;
-    token_market.minimum_offer = minimum_offer;
-
-    if !token_market.bidding_price.is_empty()
-        && token_market.minimum_offer <= *token_market.bidding_price.last().unwrap()
-    {
-        let contract_purse = helpers::get_uref(CONTRACT_PURSE);
-
-        let bidder = *token_market.bidder.last().unwrap();
-        let bidding_price = *token_market.bidding_price.last().unwrap();
+    events::emit(&MarketPlaceEvent::ConfigChangeExecuted { config, value });
+}
 
-        do_trade_change_price(
-            &mut token_market,
-            contract_purse,
-            bidder,
-            bidding_price,
+#[no_mangle]
+pub extern "C" fn cancel_config_change() {
+    roles::require(ROLE_FEE_MANAGER);
+    let config: u8 = runtime::get_named_arg(ARG_CONFIG);
+
+    let value = match config {
+        CONFIG_MARKET_FEE => {
+            let (new_fee, _) = get_key::<Option<(U256, u64)>>(PENDING_MARKET_FEE)
+                .flatten()
+                .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            set_key(PENDING_MARKET_FEE, None::<(U256, u64)>);
+            new_fee.to_string()
+        }
+        CONFIG_MARKET_FEE_RECEIVER => {
+            let (fee_receiver, _) = get_key::<Option<(Key, u64)>>(PENDING_MARKET_FEE_RECEIVER)
+                .flatten()
+                .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            set_key(PENDING_MARKET_FEE_RECEIVER, None::<(Key, u64)>);
+            fee_receiver.to_string()
+        }
+        CONFIG_FEE_RECIPIENTS => {
+            let (fee_recipients, _) =
+                get_key::<Option<(FeeRecipients, u64)>>(PENDING_FEE_RECIPIENTS)
+                    .flatten()
+                    .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            set_key(PENDING_FEE_RECIPIENTS, None::<(FeeRecipients, u64)>);
+            fee_recipients_to_string(&fee_recipients)
+        }
+        CONFIG_MIGRATION_TARGET => {
+            let (target_marketplace, _) = get_key::<Option<(Key, u64)>>(PENDING_MIGRATION_TARGET)
+                .flatten()
+                .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            set_key(PENDING_MIGRATION_TARGET, None::<(Key, u64)>);
+            target_marketplace.to_string()
+        }
+        CONFIG_FEE_OVERRIDE => {
+            let pending_key = get_pending_fee_override_key_arg();
+            let (change, _) = get_dictionary_value_from_key::<Option<(FeeOverrideChange, u64)>>(
+                PENDING_FEE_OVERRIDES,
+                &pending_key,
+            )
+            .flatten()
+            .unwrap_or_revert_with(Error::NoPendingConfigChange);
+            write_dictionary_value_from_key(
+                PENDING_FEE_OVERRIDES,
+                &pending_key,
+                None::<(FeeOverrideChange, u64)>,
+            );
+            fee_override_to_string(change)
+        }
+        _ => runtime::revert(Error::InvalidConfig),
+    };
+
+    events::emit(&MarketPlaceEvent::ConfigChangeCancelled { config, value });
+}
+
+#[no_mangle]
+pub extern "C" fn grant_role() {
+    let caller = roles::require(ROLE_ADMIN);
+    let account: Key = runtime::get_named_arg(ARG_ACCOUNT);
+    let role: u8 = runtime::get_named_arg(ARG_ROLE);
+    if role == 0 || role & !ROLE_ALL != 0 {
+        runtime::revert(Error::InvalidRole);
+    }
+    roles::set_roles(account, roles::roles_of(account) | role);
+
+    events::emit(&MarketPlaceEvent::RoleGranted {
+        account,
+        role,
+        sender: caller,
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn revoke_role() {
+    let caller = roles::require(ROLE_ADMIN);
+    let account: Key = runtime::get_named_arg(ARG_ACCOUNT);
+    let role: u8 = runtime::get_named_arg(ARG_ROLE);
+    if role == 0 || role & !ROLE_ALL != 0 {
+        runtime::revert(Error::InvalidRole);
+    }
+    roles::set_roles(account, roles::roles_of(account) & !role);
+
+    events::emit(&MarketPlaceEvent::RoleRevoked {
+        account,
+        role,
+        sender: caller,
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn set_paused() {
+    roles::require(ROLE_PAUSER);
+    let paused: u8 = runtime::get_named_arg(ARG_PAUSED);
+    if paused & !PAUSE_ALL != 0 {
+        runtime::revert(Error::InvalidPauseFlags);
+    }
+    set_key(PAUSED, paused);
+
+    events::emit(&MarketPlaceEvent::PauseUpdated {
+        nft_contract: None,
+        paused,
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn set_collection_paused() {
+    roles::require(ROLE_PAUSER);
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let paused: u8 = runtime::get_named_arg(ARG_PAUSED);
+    if paused & !PAUSE_ALL != 0 {
+        runtime::revert(Error::InvalidPauseFlags);
+    }
+    write_dictionary_value_from_key(
+        COLLECTION_PAUSED,
+        &helpers::make_dictionary_item_key_for_key(nft_contract_hash),
+        paused,
+    );
+
+    events::emit(&MarketPlaceEvent::PauseUpdated {
+        nft_contract: Some(nft_contract_hash),
+        paused,
+    });
+}
+
+// Share of the market fee, in basis points, paid to the referrer of a trade.
+#[no_mangle]
+pub extern "C" fn set_referral_share() {
+    roles::require(ROLE_FEE_MANAGER);
+    let referral_share_bps: U256 = runtime::get_named_arg(ARG_REFERRAL_SHARE_BPS);
+    if referral_share_bps > U256::from(BPS_DIVISOR) {
+        runtime::revert(Error::InvalidReferralShare);
+    }
+    set_key(REFERRAL_SHARE_BPS, referral_share_bps);
+
+    events::emit(&MarketPlaceEvent::ReferralShareUpdated { referral_share_bps });
+}
+
+// A fee override change: the collection, or `None` for the market-wide route overrides, the route
+// and the new fee, with `None` removing the override.
+type FeeOverrideChange = (Option<Key>, u8, Option<U256>);
+
+// Sets or, without `fee_bps`, removes the fee of a trade route, market-wide or for the collection
+// passed as `nft_contract_hash`. Overrides can only go down to the market fee. A change that
+// lowers fees takes effect right away; one that raises the fee of any trade route, including
+// removing an override below the fee it falls back to, is queued, see `execute_config_change`.
+#[no_mangle]
+pub extern "C" fn set_fee_override() {
+    roles::require(ROLE_FEE_MANAGER);
+    let (nft_contract, route) = get_fee_override_target_args();
+    let fee_bps: Option<U256> = runtime::get_named_arg(ARG_FEE_BPS);
+
+    let is_trade_route = (TRADE_ROUTE_BUY..=TRADE_ROUTE_PRICE_CROSSING).contains(&route);
+    if !is_trade_route && !(route == FEE_ROUTE_ANY && nft_contract.is_some()) {
+        runtime::revert(Error::InvalidFeeRoute);
+    }
+    let market_fee: U256 = helpers::get_stored_value_with_user_errors(
+        MARKET_FEE_BPS,
+        Error::MissingFeePortion,
+        Error::InvalidFeePortion,
+    );
+    if matches!(fee_bps, Some(fee) if fee > market_fee) {
+        runtime::revert(Error::FeeTooHigh);
+    }
+
+    let change = (nft_contract, route, fee_bps);
+    if !raises_fee(change, market_fee) {
+        write_fee_override(change);
+        return;
+    }
+    let effective_at = get_effective_time_arg();
+    let pending_key = pending_fee_override_key(nft_contract, route);
+    if let Some((replaced, _)) = get_dictionary_value_from_key::<Option<(FeeOverrideChange, u64)>>(
+        PENDING_FEE_OVERRIDES,
+        &pending_key,
+    )
+    .flatten()
+    {
+        events::emit(&MarketPlaceEvent::ConfigChangeCancelled {
+            config: CONFIG_FEE_OVERRIDE,
+            value: fee_override_to_string(replaced),
+        });
+    }
+    write_dictionary_value_from_key(
+        PENDING_FEE_OVERRIDES,
+        &pending_key,
+        Some((change, effective_at)),
+    );
+
+    events::emit(&MarketPlaceEvent::ConfigChangeScheduled {
+        config: CONFIG_FEE_OVERRIDE,
+        value: fee_override_to_string(change),
+        effective_at,
+    });
+}
+
+// The trade route of a fee override and its collection, market-wide without `nft_contract_hash`.
+// `execute_config_change` and `cancel_config_change` take them too to name a pending override.
+fn get_fee_override_target_args() -> (Option<Key>, u8) {
+    let route: u8 = runtime::get_named_arg(ARG_ROUTE);
+    let nft_contract = if helpers::get_named_arg_size(ARG_NFT_CONTRACT_HASH).is_some() {
+        Some(runtime::get_named_arg::<Key>(ARG_NFT_CONTRACT_HASH))
+    } else {
+        None
+    };
+    (nft_contract, route)
+}
+
+fn get_pending_fee_override_key_arg() -> String {
+    let (nft_contract, route) = get_fee_override_target_args();
+    pending_fee_override_key(nft_contract, route)
+}
+
+// Overrides are pending per collection and route, so changes to different ones don't replace
+// each other.
+fn pending_fee_override_key(nft_contract: Option<Key>, route: u8) -> String {
+    let mut preimage = nft_contract.to_bytes().unwrap_or_revert();
+    preimage.push(route);
+    base64::encode(&preimage)
+}
+
+// Whether the change raises the fee of a trade route in its collection or, for a market-wide
+// change, in collections without an override of their own.
+fn raises_fee(change: FeeOverrideChange, market_fee: U256) -> bool {
+    let (nft_contract, _, _) = change;
+    let current = fee_overrides_of(nft_contract);
+    let updated = with_fee_override(current.clone(), change);
+    let route_fees = route_fee_overrides();
+    (TRADE_ROUTE_BUY..=TRADE_ROUTE_PRICE_CROSSING).any(|route| match nft_contract {
+        Some(_) => {
+            resolve_fee(&updated, &route_fees, market_fee, route)
+                > resolve_fee(&current, &route_fees, market_fee, route)
+        }
+        None => {
+            resolve_fee(&[], &updated, market_fee, route)
+                > resolve_fee(&[], &current, market_fee, route)
+        }
+    })
+}
+
+fn fee_overrides_of(nft_contract: Option<Key>) -> FeeOverrides {
+    match nft_contract {
+        Some(nft_contract_hash) => collection_fee_overrides(nft_contract_hash),
+        None => route_fee_overrides(),
+    }
+}
+
+fn with_fee_override(mut overrides: FeeOverrides, change: FeeOverrideChange) -> FeeOverrides {
+    let (_, route, fee_bps) = change;
+    overrides.retain(|&(overridden_route, _)| overridden_route != route);
+    if let Some(fee) = fee_bps {
+        overrides.push((route, fee));
+    }
+    overrides
+}
+
+fn write_fee_override(change: FeeOverrideChange) {
+    let (nft_contract, route, fee_bps) = change;
+    let overrides = with_fee_override(fee_overrides_of(nft_contract), change);
+    match nft_contract {
+        Some(nft_contract_hash) => write_dictionary_value_from_key(
+            COLLECTION_FEES,
+            &helpers::make_dictionary_item_key_for_key(nft_contract_hash),
+            overrides,
+        ),
+        None => set_key(ROUTE_FEES, overrides),
+    }
+
+    events::emit(&MarketPlaceEvent::FeeOverrideUpdated {
+        nft_contract,
+        route,
+        fee_bps,
+    });
+}
+
+fn fee_override_to_string((nft_contract, route, fee_bps): FeeOverrideChange) -> String {
+    let scope = nft_contract.map_or_else(|| "market".to_string(), |key| key.to_string());
+    let fee = fee_bps.map_or_else(|| "none".to_string(), |fee| fee.to_string());
+    scope + ":" + &route.to_string() + ":" + &fee
+}
+
+#[no_mangle]
+pub extern "C" fn set_max_bids_per_token() {
+    roles::require(ROLE_ADMIN);
+    let max_bids_per_token: u32 = runtime::get_named_arg(ARG_MAX_BIDS_PER_TOKEN);
+    if max_bids_per_token == 0 || max_bids_per_token > MAX_BIDS_PER_TOKEN_LIMIT {
+        runtime::revert(Error::InvalidMaxBidsPerToken);
+    }
+    set_key(MAX_BIDS_PER_TOKEN, max_bids_per_token);
+
+    events::emit(&MarketPlaceEvent::MaxBidsPerTokenUpdated { max_bids_per_token });
+}
+
+#[no_mangle]
+pub extern "C" fn set_bid_config() {
+    roles::require(ROLE_CURATOR);
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let min_bid: U256 = runtime::get_named_arg(ARG_MIN_BID);
+    let min_bid_increment: U256 = runtime::get_named_arg(ARG_MIN_BID_INCREMENT);
+    let min_bid_increment_bps: U256 = runtime::get_named_arg(ARG_MIN_BID_INCREMENT_BPS);
+
+    // a zero amount is what `bid_book` reads as "no bid"
+    if min_bid.is_zero() {
+        runtime::revert(Error::InvalidMinBid);
+    }
+    if min_bid_increment_bps > U256::from(BPS_DIVISOR) {
+        runtime::revert(Error::InvalidBidIncrementBps);
+    }
+
+    let collection_key = helpers::make_dictionary_item_key_for_key(nft_contract_hash);
+    write_dictionary_value_from_key(MIN_BID, &collection_key, min_bid);
+    write_dictionary_value_from_key(MIN_BID_INCREMENT, &collection_key, min_bid_increment);
+    write_dictionary_value_from_key(
+        MIN_BID_INCREMENT_BPS,
+        &collection_key,
+        min_bid_increment_bps,
+    );
+
+    events::emit(&MarketPlaceEvent::BidConfigUpdated {
+        nft_contract: nft_contract_hash,
+        min_bid,
+        min_bid_increment,
+        min_bid_increment_bps,
+    });
+}
+
+// Registers the package hash of an older marketplace allowed to push listings into this one.
+#[no_mangle]
+pub extern "C" fn set_migration_source() {
+    roles::require(ROLE_ADMIN);
+    let source_marketplace: Key = runtime::get_named_arg(ARG_SOURCE_MARKETPLACE);
+    let enabled: bool = runtime::get_named_arg(ARG_ENABLED);
+
+    write_dictionary_value_from_key(
+        MIGRATION_SOURCES,
+        &helpers::make_dictionary_item_key_for_key(source_marketplace),
+        enabled,
+    );
+
+    events::emit(&MarketPlaceEvent::MigrationSourceUpdated {
+        source_marketplace,
+        enabled,
+    });
+}
+
+// The marketplace `migrate_listings` hands escrowed tokens and bids to. Only the owner can pick
+// it, and it goes through the config change delay like fee changes.
+#[no_mangle]
+pub extern "C" fn set_migration_target() {
+    roles::require_owner();
+    let target_marketplace: Key = runtime::get_named_arg(ARG_TARGET_MARKETPLACE);
+    if target_marketplace.into_hash().is_none() {
+        runtime::revert(Error::InvalidContext);
+    }
+    let effective_at = get_effective_time_arg();
+    cancel_replaced_change(
+        CONFIG_MIGRATION_TARGET,
+        PENDING_MIGRATION_TARGET,
+        |target_marketplace: &Key| target_marketplace.to_string(),
+    );
+    set_key(
+        PENDING_MIGRATION_TARGET,
+        Some((target_marketplace, effective_at)),
+    );
+
+    events::emit(&MarketPlaceEvent::ConfigChangeScheduled {
+        config: CONFIG_MIGRATION_TARGET,
+        value: target_marketplace.to_string(),
+        effective_at,
+    });
+}
+
+// Drops the approved target and ends a migration in progress, so listings and bids are accepted
+// again. Listings already migrated stay with the target.
+#[no_mangle]
+pub extern "C" fn clear_migration_target() {
+    roles::require_owner();
+    let target_marketplace: Key = get_key(APPROVED_MIGRATION_TARGET)
+        .or_else(|| get_key(MIGRATION_TARGET))
+        .unwrap_or_revert_with(Error::MigrationTargetNotScheduled);
+    runtime::remove_key(APPROVED_MIGRATION_TARGET);
+    runtime::remove_key(MIGRATION_TARGET);
+
+    events::emit(&MarketPlaceEvent::MigrationTargetCleared { target_marketplace });
+}
+
+// Moves listings of one collection, with their escrowed token and bids, to the target approved
+// through `set_migration_target`. Tokens are handled one by one and each migrated listing is
+// recorded, so a migration too large for one deploy is resumed by sending the remaining (or the
+// same) token ids again. Once started, new listings and bids are refused until the migration
+// target is cleared.
+#[no_mangle]
+pub extern "C" fn migrate_listings() {
+    roles::require(ROLE_ADMIN);
+    check_not_shut_down();
+    let target_marketplace: Key = runtime::get_named_arg(ARG_TARGET_MARKETPLACE);
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let token_ids: Vec<String> = runtime::get_named_arg(ARG_TOKEN_IDS);
+
+    check_enabled_nft(nft_contract_hash);
+    if get_key::<Key>(APPROVED_MIGRATION_TARGET) != Some(target_marketplace) {
+        runtime::revert(Error::MigrationTargetNotScheduled);
+    }
+
+    match get_key::<Key>(MIGRATION_TARGET) {
+        Some(migration_target) if migration_target != target_marketplace => {
+            runtime::revert(Error::MigrationTargetMismatch)
+        }
+        Some(_) => {}
+        None => set_key(MIGRATION_TARGET, target_marketplace),
+    }
+
+    let target_contract_hash = target_marketplace
+        .into_hash()
+        .map(ContractHash::new)
+        .unwrap_or_revert_with(Error::InvalidContext);
+    let contract_purse = helpers::get_uref(CONTRACT_PURSE);
+
+    for token_id in token_ids {
+        let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+        if get_dictionary_value_from_key::<Key>(MIGRATED_LISTINGS, &market_key).is_some() {
+            continue;
+        }
+        let mut token_market = match get_selling_in_market(&market_key) {
+            Some(token_market) => token_market,
+            None => continue,
+        };
+
+        let bids = bid_book::bids(&market_key);
+        let bid_total = bids
+            .iter()
+            .fold(U256::zero(), |total, &(_, bidding_price)| {
+                total + bidding_price
+            });
+
+        // the target pulls the escrowed bids from a purse handed over with the listing
+        let bids_purse = system::create_purse();
+        if !bid_total.is_zero() {
+            transfer_from_purse_to_purse(contract_purse, bids_purse, u256_to_u512(bid_total), None)
+                .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+        }
+
+        let target_escrow: Key = runtime::call_contract(
+            target_contract_hash,
+            IMPORT_LISTING_ENTRY_POINT_NAME,
+            runtime_args! {
+                ARG_NFT_CONTRACT_HASH => nft_contract_hash,
+                ARG_TOKEN_ID => token_id.clone(),
+                ARG_SELLER => token_market.offeror,
+                ARG_MINIMUM_OFFER => token_market.minimum_offer,
+                ARG_IS_ACTIVE => token_market.is_active,
+                ARG_BIDS => bids.clone(),
+                ARG_SRC_PURSE => bids_purse
+            },
+        );
+
+        if token_market.is_active {
+            nft_transfer_from(
+                &nft_contract_hash,
+                escrow_key(nft_contract_hash),
+                target_escrow,
+                token_id.clone(),
+            );
+        }
+        bid_book::clear_bids(&market_key);
+
+        let offeror = token_market.offeror;
+        token_market.is_active = false;
+        token_market.offeror = None;
+        write_dictionary_value_from_key(SELLING_IN_MARKET, &market_key, token_market.clone());
+        write_dictionary_value_from_key(MIGRATED_LISTINGS, &market_key, target_marketplace);
+        listing_index::remove(nft_contract_hash, &token_id);
+        market_stats::listing_removed(nft_contract_hash, &token_id);
+
+        events::emit(&MarketPlaceEvent::ListingMigrated {
+            nft_contract: nft_contract_hash,
+            token_id,
+            offeror: offeror.unwrap_or_else(null_key),
+            minimum_offer: token_market.minimum_offer,
+            target_marketplace,
+            bid_count: bids.len() as u32,
+            bid_total,
+        });
+    }
+}
+
+// Receiving end of `migrate_listings`, only callable by a registered source marketplace.
+// Returns the key the escrowed token has to be sent to.
+#[no_mangle]
+pub extern "C" fn import_listing() {
+    check_not_shut_down();
+    let source_marketplace = get_immediate_caller_key();
+    let is_source = get_dictionary_value_from_key::<bool>(
+        MIGRATION_SOURCES,
+        &helpers::make_dictionary_item_key_for_key(source_marketplace),
+    )
+    .unwrap_or_default();
+    if !is_source {
+        runtime::revert(Error::InvalidMigrationSource);
+    }
+
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    check_enabled_nft(nft_contract_hash);
+    let token_id: String = runtime::get_named_arg(ARG_TOKEN_ID);
+    let offeror: Option<Key> = runtime::get_named_arg(ARG_SELLER);
+    let minimum_offer: U256 = runtime::get_named_arg(ARG_MINIMUM_OFFER);
+    let is_active: bool = runtime::get_named_arg(ARG_IS_ACTIVE);
+    let bids: Vec<(Key, U256)> = runtime::get_named_arg(ARG_BIDS);
+    let src_purse: URef = runtime::get_named_arg(ARG_SRC_PURSE);
+
+    let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+    if let Some(token_market) = get_selling_in_market(&market_key) {
+        if token_market.is_active {
+            runtime::revert(Error::ListingAlreadyActive);
+        }
+    }
+
+    let bid_total = bids
+        .iter()
+        .fold(U256::zero(), |total, &(_, bidding_price)| {
+            total + bidding_price
+        });
+    if !bid_total.is_zero() {
+        let contract_purse = helpers::get_uref(CONTRACT_PURSE);
+        transfer_from_purse_to_purse(src_purse, contract_purse, u256_to_u512(bid_total), None)
+            .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+    }
+
+    let token_market = SellingInMarket {
+        token_id: token_id.clone(),
+        nft_contract: nft_contract_hash,
+        offeror,
+        minimum_offer,
+        is_active,
+        legacy_bids: Vec::new(),
+    };
+
+    // a bidder who already bid here keeps a single bid worth both, and bids that do not fit in
+    // the book are refunded
+    if !bids.is_empty() {
+        let contract_purse = helpers::get_uref(CONTRACT_PURSE);
+        for &(bidder, bidding_price) in &bids {
+            if let Some(left_out) = bid_book::merge_bid(&market_key, bidder, bidding_price) {
+                refund_evicted_bid(contract_purse, &token_market, left_out);
+            }
+        }
+    }
+    write_dictionary_value_from_key(SELLING_IN_MARKET, &market_key, token_market);
+    // the source sends the token as soon as this returns
+    if is_active && nft_standard(nft_contract_hash) != NFT_STANDARD_CEP47 {
+        count_cep78_escrow(1, 0);
+    }
+    if let (Some(seller), true) = (offeror, is_active) {
+        listing_index::insert(seller, nft_contract_hash, &token_id);
+        market_stats::listing_priced(nft_contract_hash, &token_id, minimum_offer);
+    }
+
+    events::emit(&MarketPlaceEvent::ListingImported {
+        nft_contract: nft_contract_hash,
+        token_id: token_id.clone(),
+        offeror: offeror.unwrap_or_else(null_key),
+        minimum_offer,
+        is_active,
+        source_marketplace,
+        bid_count: bids.len() as u32,
+        bid_total,
+    });
+    // one event per bid still in the book so indexers can rebuild the imported bid book
+    for &(bidder, _) in &bids {
+        let value = match bid_book::bid_of(&market_key, bidder) {
+            Some(value) => value,
+            None => continue,
+        };
+        events::emit(&MarketPlaceEvent::Bid {
+            nft_contract: nft_contract_hash,
+            token_id: token_id.clone(),
+            offeror: offeror.unwrap_or_else(null_key),
+            bidder,
+            value,
+        });
+    }
+
+    runtime::ret(CLValue::from_t(escrow_key(nft_contract_hash)).unwrap_or_revert());
+}
+
+// Read-only queries, answering through `runtime::ret` so other contracts and session code can use
+// them. A token that was never listed reads as `None` or as no bids.
+
+#[no_mangle]
+pub extern "C" fn get_listing() {
+    let market_key = get_query_market_key();
+    let listing = get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &market_key)
+        .map(|token_market| token_market.to_v1());
+    runtime::ret(CLValue::from_t(listing).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_bid() {
+    let market_key = get_query_market_key();
+    let bidder: Key = runtime::get_named_arg(ARG_BIDDER);
+    // a bid still held in a legacy record is merged into the book when the listing is next read
+    let legacy_price =
+        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &market_key)
+            .into_iter()
+            .flat_map(|token_market| token_market.legacy_bids)
+            .filter(|&(key, _)| key == bidder)
+            .fold(U256::zero(), |total, (_, price)| total + price);
+    let bidding_price = bid_book::bid_of(&market_key, bidder).unwrap_or_default() + legacy_price;
+    runtime::ret(CLValue::from_t(bidding_price).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_top_bids() {
+    let market_key = get_query_market_key();
+    let count: u32 = runtime::get_named_arg(ARG_COUNT);
+    let top_bids: Vec<(Key, U256)> = listing_bids(&market_key)
+        .into_iter()
+        .take(count as usize)
+        .collect();
+    runtime::ret(CLValue::from_t(top_bids).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_market_config() {
+    let contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let market_fee: U256 = helpers::get_stored_value_with_user_errors(
+        MARKET_FEE_BPS,
+        Error::MissingFeePortion,
+        Error::InvalidFeePortion,
+    );
+    let fee_recipients: FeeRecipients = helpers::get_stored_value_with_user_errors(
+        FEE_RECIPIENTS,
+        Error::MissingFeeReceiver,
+        Error::InvalidFeeReceiver,
+    );
+    let config: MarketConfig = (
+        (contract_owner, market_fee, fee_recipients),
+        (
+            get_key::<u8>(PAUSED).unwrap_or_default(),
+            is_shut_down(),
+            bid_book::max_bids_per_token(),
+        ),
+        route_fee_overrides(),
+    );
+    runtime::ret(CLValue::from_t(config).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_collection_config() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let collection_key = helpers::make_dictionary_item_key_for_key(nft_contract_hash);
+    let config: CollectionConfig = (
+        (
+            supported_nft_standard(nft_contract_hash),
+            get_dictionary_value_from_key::<u8>(COLLECTION_PAUSED, &collection_key)
+                .unwrap_or_default(),
+        ),
+        (
+            get_dictionary_value_from_key::<U256>(MIN_BID, &collection_key)
+                .unwrap_or_else(|| U256::from(DEFAULT_MIN_BID)),
+            get_dictionary_value_from_key::<U256>(MIN_BID_INCREMENT, &collection_key)
+                .unwrap_or_default(),
+            get_dictionary_value_from_key::<U256>(MIN_BID_INCREMENT_BPS, &collection_key)
+                .unwrap_or_default(),
+        ),
+        collection_fee_overrides(nft_contract_hash),
+    );
+    runtime::ret(CLValue::from_t(config).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_effective_fee() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let route: u8 = runtime::get_named_arg(ARG_ROUTE);
+    runtime::ret(CLValue::from_t(market_fee_bps(nft_contract_hash, route)).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_seller_listings() {
+    let seller: Key = runtime::get_named_arg(ARG_SELLER);
+    let (offset, limit) = get_page_args();
+    let page = listing_index::seller_listings(seller, offset, limit);
+    runtime::ret(CLValue::from_t(page).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_collection_listings() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let (offset, limit) = get_page_args();
+    let page = listing_index::collection_listings(nft_contract_hash, offset, limit);
+    runtime::ret(CLValue::from_t(page).unwrap_or_revert());
+}
+
+// Adds active listings made before the indexes existed. Indexing only mirrors the listing
+// records, so anyone may call it, and listings already indexed or inactive are skipped.
+#[no_mangle]
+pub extern "C" fn index_listings() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let token_ids: Vec<String> = runtime::get_named_arg(ARG_TOKEN_IDS);
+
+    for token_id in token_ids {
+        let market_key = get_token_market_key(&nft_contract_hash, &token_id);
+        let token_market =
+            get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &market_key);
+        if let Some(SellingInMarket {
+            offeror: Some(seller),
+            minimum_offer,
+            is_active: true,
+            ..
+        }) = token_market
+        {
+            listing_index::insert(seller, nft_contract_hash, &token_id);
+            market_stats::listing_priced(nft_contract_hash, &token_id, minimum_offer);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn get_collection_stats() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let stats: (market_stats::CollectionStats, market_stats::Floor) = (
+        market_stats::collection_stats(nft_contract_hash),
+        market_stats::floor(nft_contract_hash),
+    );
+    runtime::ret(CLValue::from_t(stats).unwrap_or_revert());
+}
+
+// Continues the search for a collection's floor after its cheapest listing left the market or got
+// more expensive. It only reads listing records, so anyone may call it.
+#[no_mangle]
+pub extern "C" fn refresh_floor() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let limit: u32 = runtime::get_named_arg(ARG_LIMIT);
+    market_stats::refresh_floor(nft_contract_hash, limit);
+}
+
+#[no_mangle]
+pub extern "C" fn get_last_sale() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let token_id: String = runtime::get_named_arg(ARG_TOKEN_ID);
+    let last_sale = market_stats::last_sale(nft_contract_hash, &token_id);
+    runtime::ret(CLValue::from_t(last_sale).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_twap() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let window: u64 = runtime::get_named_arg(ARG_WINDOW);
+    let min_sales: u32 = runtime::get_named_arg(ARG_MIN_SALES);
+    let price = twap::twap(nft_contract_hash, window, min_sales);
+    runtime::ret(CLValue::from_t(price).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_referral_total() {
+    let referrer: Key = runtime::get_named_arg(ARG_REFERRER);
+    let referral_total = get_dictionary_value_from_key::<U256>(
+        REFERRAL_TOTALS,
+        &helpers::make_dictionary_item_key_for_key(referrer),
+    )
+    .unwrap_or_default();
+    runtime::ret(CLValue::from_t(referral_total).unwrap_or_revert());
+}
+
+fn get_page_args() -> (u32, u32) {
+    (
+        runtime::get_named_arg(ARG_OFFSET),
+        runtime::get_named_arg(ARG_LIMIT),
+    )
+}
+
+fn get_query_market_key() -> String {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let token_id: String = runtime::get_named_arg(ARG_TOKEN_ID);
+    get_token_market_key(&nft_contract_hash, &token_id)
+}
+
+// Bids on a listing, highest first, including any still held in a legacy record. Unlike
+// `get_selling_in_market` this writes nothing.
+fn listing_bids(market_key: &str) -> Vec<(Key, U256)> {
+    let mut bids = bid_book::bids(market_key);
+    if let Some(token_market) =
+        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, market_key)
+    {
+        bids.extend(token_market.legacy_bids);
+    }
+    bids.sort_by(|(_, a), (_, b)| b.cmp(a));
+    bids
+}
+
+// Reads a listing, moving any bids still stored in the record itself into the bid book and
+// refunding those that do not fit in it.
+fn get_selling_in_market(market_key: &str) -> Option<SellingInMarket> {
+    let mut token_market =
+        get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, market_key)?;
+
+    if !token_market.legacy_bids.is_empty() {
+        let legacy_bids: Vec<(Key, U256)> = token_market.legacy_bids.drain(..).collect();
+        write_dictionary_value_from_key(SELLING_IN_MARKET, market_key, token_market.clone());
+        let contract_purse = helpers::get_uref(CONTRACT_PURSE);
+        for (bidder, bidding_price) in legacy_bids {
+            if let Some(left_out) = bid_book::merge_bid(market_key, bidder, bidding_price) {
+                refund_evicted_bid(contract_purse, &token_market, left_out);
+            }
+        }
+    }
+    Some(token_market)
+}
+
+// Refunds a bid that was left out of a full bid book.
+fn refund_evicted_bid(
+    contract_purse: URef,
+    token_market: &SellingInMarket,
+    (bidder, value): (Key, U256),
+) {
+    transfer_from_purse_to_account(
+        contract_purse,
+        bidder.into_account().unwrap_or_revert(),
+        u256_to_u512(value),
+        None,
+    )
+    .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+
+    events::emit(&MarketPlaceEvent::RevokeBid {
+        nft_contract: token_market.nft_contract,
+        token_id: token_market.token_id.clone(),
+        offeror: token_market.offeror.unwrap_or_else(null_key),
+        bidder,
+        value,
+        is_revoke: false,
+    });
+    events::emit(&MarketPlaceEvent::Refund {
+        nft_contract: token_market.nft_contract,
+        token_id: token_market.token_id.clone(),
+        recipient: bidder,
+        value,
+        reason: REFUND_BID_EVICTED,
+    });
+}
+
+fn set_selling(contract_hash: &Key, token_identifier: &str, offeror: Key, minimum_offer: U256) {
+    let market_key = get_token_market_key(contract_hash, token_identifier);
+    let exists = get_selling_in_market(&market_key);
+    let mut token_market = if let Some(..) = exists {
+        exists.unwrap()
+    } else {
+        SellingInMarket {
+            offeror: Some(offeror),
+            token_id: token_identifier.to_string(),
+            nft_contract: *contract_hash,
+            minimum_offer,
+            is_active: true,
+            legacy_bids: Vec::new(),
+        }
+    };
+
+    token_market.offeror = Some(offeror);
+    token_market.is_active = true;
+    token_market.minimum_offer = minimum_offer;
+
+    let top_bid = bid_book::top_bid(&market_key).filter(|&(_, price)| minimum_offer <= price);
+    if let Some((bidder, bidding_price)) = top_bid {
+        check_not_paused(*contract_hash, PAUSE_BUYING);
+        let contract_purse = helpers::get_uref(CONTRACT_PURSE);
+
+        do_trade_change_price(
+            &mut token_market,
+            contract_purse,
+            bidder,
+            bidding_price,
             offeror,
         )
     } else {
-        cep78_transfer_from(
+        nft_transfer_from(
             contract_hash,
             offeror,
-            get_self_key(),
+            escrow_key(*contract_hash),
             token_identifier.to_string(),
         );
 
-        write_dictionary_value_from_key(SELLING_IN_MARKET, token_identifier, token_market.clone());
+        write_dictionary_value_from_key(SELLING_IN_MARKET, &market_key, token_market.clone());
+        listing_index::insert(offeror, *contract_hash, token_identifier);
+        market_stats::listing_priced(*contract_hash, token_identifier, minimum_offer);
 
         events::emit(&MarketPlaceEvent::Sell {
             nft_contract: *contract_hash,
@@ -887,6 +2205,21 @@ fn set_selling(contract_hash: &Key, token_identifier: &str, offeror: Key, minimu
     }
 }
 
+fn cep47_transfer_from(contract_hash: &Key, source: Key, target: Key, token_ids: Vec<String>) {
+    let contract_hash_addr: HashAddr = contract_hash.into_hash().unwrap_or_revert();
+    let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
+
+    let _: () = runtime::call_contract(
+        contract_hash,
+        TRANSFER_FROM_ENTRY_POINT_NAME,
+        runtime_args! {
+            "sender" => source,
+            "recipient" => target,
+            "token_ids" => token_ids
+        },
+    );
+}
+
 fn cep78_transfer_from(contract_hash: &Key, source: Key, target: Key, token_id: String) {
     let contract_hash_addr: HashAddr = contract_hash.into_hash().unwrap_or_revert();
     let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
@@ -902,30 +2235,201 @@ fn cep78_transfer_from(contract_hash: &Key, source: Key, target: Key, token_id:
     );
 }
 
-fn check_enabled_nft(contract_hash: Key) {
+fn nft_transfer_from(contract_hash: &Key, source: Key, target: Key, token_id: String) {
+    match nft_standard(*contract_hash) {
+        NFT_STANDARD_CEP47 => cep47_transfer_from(contract_hash, source, target, vec![token_id]),
+        _ => {
+            cep78_transfer_from(contract_hash, source, target, token_id);
+            let escrow = get_self_contract_key();
+            count_cep78_escrow((target == escrow) as u64, (source == escrow) as u64);
+        }
+    }
+}
+
+// CEP-78 only lets the contract hash holding a token move it, while CEP-47 sees the package hash,
+// which every contract version shares.
+fn escrow_key(contract_hash: Key) -> Key {
+    match nft_standard(contract_hash) {
+        NFT_STANDARD_CEP47 => get_self_key(),
+        _ => get_self_contract_key(),
+    }
+}
+
+fn count_cep78_escrow(added: u64, removed: u64) {
+    let escrowed = get_key::<u64>(ESCROWED_CEP78_TOKENS).unwrap_or_default();
+    set_key(ESCROWED_CEP78_TOKENS, escrowed + added - removed);
+}
+
+// The NFT standard of a collection, 0 when the collection is not supported.
+fn supported_nft_standard(contract_hash: Key) -> u8 {
     let nft_contract_hash: Key = helpers::get_key(TOKEN_CONTRACT_SUPPORT).unwrap();
 
-    if nft_contract_hash != contract_hash {
+    if nft_contract_hash == contract_hash {
+        return NATIVE_NFT_STANDARD;
+    }
+    get_dictionary_value_from_key::<u8>(
+        NFT_SUPPPORTED_CONTRACTS,
+        &helpers::make_dictionary_item_key_for_key(contract_hash),
+    )
+    .unwrap_or_default()
+}
+
+fn nft_standard(contract_hash: Key) -> u8 {
+    let nft_standard = supported_nft_standard(contract_hash);
+    if nft_standard == 0 {
         runtime::revert(Error::UnsupportedToken);
     }
+    nft_standard
+}
+
+fn check_enabled_nft(contract_hash: Key) {
+    nft_standard(contract_hash);
+}
+
+// `change_price` and `accept_price` predate collections other than the native one, so their
+// collection argument is optional.
+fn get_optional_nft_contract_arg() -> Key {
+    if helpers::get_named_arg_size(ARG_NFT_CONTRACT_HASH).is_some() {
+        let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+        check_enabled_nft(nft_contract_hash);
+        nft_contract_hash
+    } else {
+        helpers::get_key(TOKEN_CONTRACT_SUPPORT).unwrap_or_revert()
+    }
+}
+
+// Reverts when `operation` is paused for the whole market or for the collection.
+fn check_not_paused(nft_contract_hash: Key, operation: u8) {
+    check_not_shut_down();
+    let paused = get_key::<u8>(PAUSED).unwrap_or_default();
+    let collection_paused = get_dictionary_value_from_key::<u8>(
+        COLLECTION_PAUSED,
+        &helpers::make_dictionary_item_key_for_key(nft_contract_hash),
+    )
+    .unwrap_or_default();
+    if (paused | collection_paused) & operation != 0 {
+        runtime::revert(Error::MarketPaused);
+    }
+}
+
+// Effective time of a queued change: `effective_at` when given, which may not be sooner than
+// `CONFIG_CHANGE_DELAY` from now, otherwise exactly that delay.
+fn get_effective_time_arg() -> u64 {
+    let earliest = u64::from(runtime::get_blocktime()) + CONFIG_CHANGE_DELAY;
+    if helpers::get_named_arg_size(ARG_EFFECTIVE_AT).is_none() {
+        return earliest;
+    }
+    let effective_at: u64 = runtime::get_named_arg(ARG_EFFECTIVE_AT);
+    if effective_at < earliest {
+        runtime::revert(Error::EffectiveTimeTooEarly);
+    }
+    effective_at
+}
+
+// Scheduling a change replaces the one still pending for the same config, which is reported as
+// cancelled so nobody keeps waiting for it.
+fn cancel_replaced_change<T: CLTyped + FromBytes>(
+    config: u8,
+    pending_key: &str,
+    to_string: fn(&T) -> String,
+) {
+    if let Some((replaced, _)) = get_key::<Option<(T, u64)>>(pending_key).flatten() {
+        events::emit(&MarketPlaceEvent::ConfigChangeCancelled {
+            config,
+            value: to_string(&replaced),
+        });
+    }
+}
+
+// `deadline` and `max_price` are optional guards of buyers and bidders against the deploy running
+// late or after the seller raised the price.
+fn check_deadline() {
+    if helpers::get_named_arg_size(ARG_DEADLINE).is_none() {
+        return;
+    }
+    let deadline: u64 = runtime::get_named_arg(ARG_DEADLINE);
+    if u64::from(runtime::get_blocktime()) > deadline {
+        runtime::revert(Error::DeadlinePassed);
+    }
+}
+
+// `referrer` is optional and only taken by the buyer's side of a trade: `buy`, `bid` and
+// `increase_bid`. A seller settling against a bid cannot name one to take part of the fee, and
+// the buyer cannot refer themselves.
+fn get_referrer_arg(buyer: Key) -> Option<Key> {
+    if helpers::get_named_arg_size(ARG_REFERRER).is_none() {
+        return None;
+    }
+    let referrer: Key = runtime::get_named_arg(ARG_REFERRER);
+    if referrer == buyer {
+        runtime::revert(Error::InvalidReferrer);
+    }
+    Some(referrer)
+}
+
+// Reverts when the trade would settle above `max_price`.
+fn check_max_price(price: U256) {
+    if helpers::get_named_arg_size(ARG_MAX_PRICE).is_none() {
+        return;
+    }
+    let max_price: U256 = runtime::get_named_arg(ARG_MAX_PRICE);
+    if price > max_price {
+        runtime::revert(Error::PriceAboveMax);
+    }
+}
+
+fn is_shut_down() -> bool {
+    get_key::<bool>(EMERGENCY_SHUTDOWN).unwrap_or_default()
+}
+
+fn check_not_shut_down() {
+    if is_shut_down() {
+        runtime::revert(Error::MarketShutdown);
+    }
 }
+
+fn check_not_migrating() {
+    if runtime::get_key(MIGRATION_TARGET).is_some() {
+        runtime::revert(Error::MarketplaceMigrating);
+    }
+}
+
 fn do_trade(
     selling_in_maket: &mut SellingInMarket,
     contract_purse: URef,
     bidder: Key,
     bidding_price: U256,
+    route: u8,
+    referrer: Option<Key>,
 ) {
-    transfer_to_seller_and_fee(selling_in_maket, contract_purse, bidding_price);
+    let (fee, seller_amount) = transfer_to_seller_and_fee(
+        selling_in_maket,
+        contract_purse,
+        bidding_price,
+        route,
+        referrer,
+    );
 
-    transfer_nfts_to_bidder(selling_in_maket, bidder, get_self_key());
+    transfer_nfts_to_bidder(
+        selling_in_maket,
+        bidder,
+        escrow_key(selling_in_maket.nft_contract),
+    );
     //dont touch the bidder list here, as the success bidder is already removed from the list
 
     selling_in_maket.is_active = false;
     let offeror = selling_in_maket.offeror.unwrap();
     selling_in_maket.offeror = None;
+    listing_index::remove(selling_in_maket.nft_contract, &selling_in_maket.token_id);
+    market_stats::listing_removed(selling_in_maket.nft_contract, &selling_in_maket.token_id);
+    market_stats::record_sale(
+        selling_in_maket.nft_contract,
+        &selling_in_maket.token_id,
+        bidding_price,
+    );
     write_dictionary_value_from_key(
         SELLING_IN_MARKET,
-        &selling_in_maket.token_id.clone(),
+        &selling_in_maket.market_key(),
         selling_in_maket.clone(),
     );
     events::emit(&MarketPlaceEvent::DoneSell {
@@ -934,6 +2438,9 @@ fn do_trade(
         offeror,
         buyer: bidder,
         value: bidding_price,
+        fee,
+        seller_amount,
+        route,
     });
 }
 
@@ -941,14 +2448,12 @@ fn transfer_to_seller_and_fee(
     selling_in_maket: &SellingInMarket,
     contract_purse: URef,
     trade_price: U256,
-) {
+    route: u8,
+    referrer: Option<Key>,
+) -> (U256, U256) {
     // print(&trade_price.to_string());
-    let fee_portion: U256 = helpers::get_stored_value_with_user_errors(
-        MARKET_FEE,
-        Error::MissingFeePortion,
-        Error::InvalidFeePortion,
-    );
-    let fee_amount_per_side = trade_price * fee_portion / U256::from(1000u64);
+    let fee_portion = market_fee_bps(selling_in_maket.nft_contract, route);
+    let fee_amount_per_side = trade_price * fee_portion / U256::from(BPS_DIVISOR);
 
     let seller_receive_amount: U256 = trade_price - fee_amount_per_side;
 
@@ -965,23 +2470,142 @@ fn transfer_to_seller_and_fee(
     .unwrap_or_revert_with(Error::CanNotTransferCSPR);
 
     let total_fee: U256 = fee_amount_per_side;
-    let fee_receiver: Key = helpers::get_stored_value_with_user_errors(
-        MARKET_FEE_RECEIVER,
+    let referral_fee = pay_referral_fee(selling_in_maket, contract_purse, total_fee, referrer);
+    pay_fee_recipients(contract_purse, total_fee - referral_fee);
+
+    (total_fee, seller_receive_amount)
+}
+
+// Fee in basis points of a trade on `route` in the collection: the collection override for the
+// route, then the collection-wide one, then the market-wide route override, and the market fee
+// when none is set. The market fee is a ceiling in case it was lowered below an override.
+fn market_fee_bps(nft_contract_hash: Key, route: u8) -> U256 {
+    let market_fee: U256 = helpers::get_stored_value_with_user_errors(
+        MARKET_FEE_BPS,
+        Error::MissingFeePortion,
+        Error::InvalidFeePortion,
+    );
+    resolve_fee(
+        &collection_fee_overrides(nft_contract_hash),
+        &route_fee_overrides(),
+        market_fee,
+        route,
+    )
+}
+
+fn resolve_fee(
+    collection_fees: &[(u8, U256)],
+    route_fees: &[(u8, U256)],
+    market_fee: U256,
+    route: u8,
+) -> U256 {
+    find_fee_override(collection_fees, route)
+        .or_else(|| find_fee_override(collection_fees, FEE_ROUTE_ANY))
+        .or_else(|| find_fee_override(route_fees, route))
+        .map_or(market_fee, |fee| fee.min(market_fee))
+}
+
+fn find_fee_override(overrides: &[(u8, U256)], route: u8) -> Option<U256> {
+    overrides
+        .iter()
+        .find(|&&(overridden_route, _)| overridden_route == route)
+        .map(|&(_, fee)| fee)
+}
+
+fn route_fee_overrides() -> FeeOverrides {
+    get_key::<FeeOverrides>(ROUTE_FEES).unwrap_or_default()
+}
+
+fn collection_fee_overrides(nft_contract_hash: Key) -> FeeOverrides {
+    get_dictionary_value_from_key::<FeeOverrides>(
+        COLLECTION_FEES,
+        &helpers::make_dictionary_item_key_for_key(nft_contract_hash),
+    )
+    .unwrap_or_default()
+}
+
+// Splits `amount` between the fee recipients by share. The last one also takes the rounding dust
+// so the whole amount leaves the contract purse.
+fn pay_fee_recipients(contract_purse: URef, amount: U256) {
+    let fee_recipients: FeeRecipients = helpers::get_stored_value_with_user_errors(
+        FEE_RECIPIENTS,
         Error::MissingFeeReceiver,
         Error::InvalidFeeReceiver,
     );
+    let mut remaining = amount;
+    for (i, &(recipient, share)) in fee_recipients.iter().enumerate() {
+        let part = if i + 1 == fee_recipients.len() {
+            remaining
+        } else {
+            amount * share / U256::from(BPS_DIVISOR)
+        };
+        remaining -= part;
+        if part.is_zero() {
+            continue;
+        }
+        transfer_from_purse_to_account(
+            contract_purse,
+            recipient
+                .into_account()
+                .unwrap_or_revert_with(Error::InvalidFeeReceiver),
+            u256_to_u512(part),
+            None,
+        )
+        .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+    }
+}
+
+// Pays `referrer` its share of the market fee, and returns that share.
+fn pay_referral_fee(
+    selling_in_maket: &SellingInMarket,
+    contract_purse: URef,
+    fee: U256,
+    referrer: Option<Key>,
+) -> U256 {
+    let referrer = match referrer {
+        Some(referrer) => referrer,
+        None => return U256::zero(),
+    };
+    // the seller would get back part of the fee on their own sale
+    if selling_in_maket.offeror == Some(referrer) {
+        runtime::revert(Error::InvalidReferrer);
+    }
+    let referral_share_bps = get_key::<U256>(REFERRAL_SHARE_BPS).unwrap_or_default();
+    let referral_fee = fee * referral_share_bps / U256::from(BPS_DIVISOR);
+    if referral_fee.is_zero() {
+        return referral_fee;
+    }
 
     transfer_from_purse_to_account(
         contract_purse,
-        fee_receiver.into_account().unwrap(),
-        u256_to_u512(total_fee),
+        referrer
+            .into_account()
+            .unwrap_or_revert_with(Error::InvalidReferrer),
+        u256_to_u512(referral_fee),
         None,
     )
     .unwrap_or_revert_with(Error::CanNotTransferCSPR);
+
+    let referrer_key = helpers::make_dictionary_item_key_for_key(referrer);
+    let referral_total =
+        get_dictionary_value_from_key::<U256>(REFERRAL_TOTALS, &referrer_key).unwrap_or_default();
+    write_dictionary_value_from_key(
+        REFERRAL_TOTALS,
+        &referrer_key,
+        referral_total + referral_fee,
+    );
+
+    events::emit(&MarketPlaceEvent::ReferralPaid {
+        nft_contract: selling_in_maket.nft_contract,
+        token_id: selling_in_maket.token_id.clone(),
+        referrer,
+        value: referral_fee,
+    });
+    referral_fee
 }
 
 fn transfer_nfts_to_bidder(selling_in_maket: &SellingInMarket, bidder: Key, transfer_from: Key) {
-    cep78_transfer_from(
+    nft_transfer_from(
         &selling_in_maket.nft_contract,
         transfer_from,
         bidder,
@@ -996,23 +2620,34 @@ fn do_trade_change_price(
     bidding_price: U256,
     transfer_from: Key,
 ) {
-    transfer_to_seller_and_fee(selling_in_maket, contract_purse, bidding_price);
+    let (fee, seller_amount) = transfer_to_seller_and_fee(
+        selling_in_maket,
+        contract_purse,
+        bidding_price,
+        TRADE_ROUTE_PRICE_CROSSING,
+        None,
+    );
 
     transfer_nfts_to_bidder(selling_in_maket, bidder, transfer_from);
 
-    // remove the last bidder in the list here after success
-    let last_index = selling_in_maket.bidder.len() - 1;
+    // remove the winning bid from the book here after success
     selling_in_maket.minimum_offer = bidding_price;
-    selling_in_maket.bidder.remove(last_index);
-    selling_in_maket.bidding_price.remove(last_index);
+    bid_book::remove_bid(&selling_in_maket.market_key(), bidder);
 
     selling_in_maket.is_active = false;
     let offeror = selling_in_maket.offeror.unwrap();
     selling_in_maket.offeror = None;
+    listing_index::remove(selling_in_maket.nft_contract, &selling_in_maket.token_id);
+    market_stats::listing_removed(selling_in_maket.nft_contract, &selling_in_maket.token_id);
+    market_stats::record_sale(
+        selling_in_maket.nft_contract,
+        &selling_in_maket.token_id,
+        bidding_price,
+    );
 
     write_dictionary_value_from_key(
         SELLING_IN_MARKET,
-        &selling_in_maket.token_id.clone(),
+        &selling_in_maket.market_key(),
         selling_in_maket.clone(),
     );
     events::emit(&MarketPlaceEvent::DoneSell {
@@ -1021,17 +2656,41 @@ fn do_trade_change_price(
         offeror,
         buyer: bidder,
         value: bidding_price,
+        fee,
+        seller_amount,
+        route: TRADE_ROUTE_PRICE_CROSSING,
     });
 }
-fn insert_new_bidder(selling_in_maket: &mut SellingInMarket, bidder: Key, bidding_price: U256) {
-    if !selling_in_maket.bidding_price.is_empty() {
-        let idx = selling_in_maket
-            .bidding_price
-            .partition_point(|&x| x < bidding_price);
-        selling_in_maket.bidding_price.insert(idx, bidding_price);
-        selling_in_maket.bidder.insert(idx, bidder);
-    } else {
-        selling_in_maket.bidding_price.push(bidding_price);
-        selling_in_maket.bidder.push(bidder);
+// A bid must reach the collection's minimum bid and, once an increment is configured, beat the
+// highest bid of the other bidders by at least the larger of the absolute and bps increments.
+// A bid reaching the price of an active listing buys it, so the increment does not apply.
+fn check_bid_amount(selling_in_maket: &SellingInMarket, bidder: Key, bidding_price: U256) {
+    let collection_key = helpers::make_dictionary_item_key_for_key(selling_in_maket.nft_contract);
+
+    let min_bid = get_dictionary_value_from_key::<U256>(MIN_BID, &collection_key)
+        .unwrap_or_else(|| U256::from(DEFAULT_MIN_BID));
+    if bidding_price < min_bid {
+        runtime::revert(Error::BidBelowMinimum)
+    }
+
+    if selling_in_maket.is_active && bidding_price >= selling_in_maket.minimum_offer {
+        return;
+    }
+
+    let highest_bid = bid_book::highest_bid_excluding(&selling_in_maket.market_key(), bidder);
+
+    if let Some(highest_bid) = highest_bid {
+        let min_increment =
+            get_dictionary_value_from_key::<U256>(MIN_BID_INCREMENT, &collection_key)
+                .unwrap_or_default();
+        let min_increment_bps =
+            get_dictionary_value_from_key::<U256>(MIN_BID_INCREMENT_BPS, &collection_key)
+                .unwrap_or_default();
+        let bps_increment = highest_bid * min_increment_bps / U256::from(BPS_DIVISOR);
+        let increment = core::cmp::max(min_increment, bps_increment);
+
+        if !increment.is_zero() && bidding_price < highest_bid + increment {
+            runtime::revert(Error::BidIncrementTooLow)
+        }
     }
 }
@@ -0,0 +1,189 @@
+//! Per-collection market statistics.
+//!
+//! Every sale adds to its collection's volume, sale count and all-time high and is kept as the
+//! token's last sale. The floor is the cheapest active listing of the collection. A new or cheaper
+//! listing is only compared with the current floor; when the floor listing leaves the market or
+//! gets more expensive, the collection's listing index is searched for the next one, so listings
+//! made before the index existed count once they have been indexed.
+//!
+//! A trade or listing change reads at most `FLOOR_SCAN_LIMIT` listings of that search. In larger
+//! collections the floor reads as `None` until anyone finishes the search a page at a time through
+//! `refresh_floor`. The search walks the index from its end, so a listing moved down by a removal
+//! is still visited, and listings priced meanwhile are compared with the cheapest one found so far.
+use alloc::string::{String, ToString};
+
+use casper_contract::contract_api::runtime;
+use casper_types::{Key, U256};
+
+use crate::constants::*;
+use crate::helpers::{
+    get_dictionary_value_from_key, get_token_market_key, make_dictionary_item_key_for_key,
+    write_dictionary_value_from_key,
+};
+use crate::listing_index;
+use crate::twap;
+use crate::SellingInMarket;
+
+/// Total volume, number of sales and all-time high of a collection.
+pub(crate) type CollectionStats = (U256, u64, U256);
+
+/// Price and token id of the cheapest active listing of a collection.
+pub(crate) type Floor = Option<(U256, String)>;
+
+/// Price and block time of the last sale of a token.
+pub(crate) type LastSale = Option<(U256, u64)>;
+
+// Position of the collection index below which the floor search has yet to look, and the cheapest
+// listing found so far.
+type FloorScan = (u32, Floor);
+
+enum FloorUpdate {
+    Unchanged,
+    Set(Floor),
+    Search,
+}
+
+pub(crate) fn collection_stats(nft_contract: Key) -> CollectionStats {
+    get_dictionary_value_from_key::<CollectionStats>(
+        COLLECTION_STATS,
+        &make_dictionary_item_key_for_key(nft_contract),
+    )
+    .unwrap_or_default()
+}
+
+pub(crate) fn floor(nft_contract: Key) -> Floor {
+    get_dictionary_value_from_key::<Floor>(
+        COLLECTION_FLOOR,
+        &make_dictionary_item_key_for_key(nft_contract),
+    )
+    .flatten()
+}
+
+pub(crate) fn last_sale(nft_contract: Key, token_id: &str) -> LastSale {
+    get_dictionary_value_from_key::<(U256, u64)>(
+        LAST_SALE,
+        &get_token_market_key(&nft_contract, token_id),
+    )
+}
+
+/// Records a completed trade.
+pub(crate) fn record_sale(nft_contract: Key, token_id: &str, price: U256) {
+    let (volume, sales, all_time_high) = collection_stats(nft_contract);
+    write_dictionary_value_from_key(
+        COLLECTION_STATS,
+        &make_dictionary_item_key_for_key(nft_contract),
+        (volume + price, sales + 1, all_time_high.max(price)),
+    );
+    write_dictionary_value_from_key(
+        LAST_SALE,
+        &get_token_market_key(&nft_contract, token_id),
+        (price, u64::from(runtime::get_blocktime())),
+    );
+    twap::record(nft_contract, price);
+}
+
+/// Updates the floor after a listing became active or changed price.
+pub(crate) fn listing_priced(nft_contract: Key, token_id: &str, price: U256) {
+    match floor_scan(nft_contract) {
+        Some((next, cheapest)) => match priced(cheapest, token_id, price) {
+            FloorUpdate::Unchanged => {}
+            FloorUpdate::Set(cheapest) => set_floor_scan(nft_contract, Some((next, cheapest))),
+            FloorUpdate::Search => search_floor(nft_contract),
+        },
+        None => match priced(floor(nft_contract), token_id, price) {
+            FloorUpdate::Unchanged => {}
+            FloorUpdate::Set(floor) => set_floor(nft_contract, floor),
+            FloorUpdate::Search => search_floor(nft_contract),
+        },
+    }
+}
+
+/// Updates the floor after a listing left the market.
+pub(crate) fn listing_removed(nft_contract: Key, token_id: &str) {
+    let cheapest = match floor_scan(nft_contract) {
+        Some((_, cheapest)) => cheapest,
+        None => floor(nft_contract),
+    };
+    if matches!(cheapest, Some((_, cheapest_token_id)) if cheapest_token_id == token_id) {
+        search_floor(nft_contract);
+    }
+}
+
+/// Continues a pending floor search of the collection by up to `limit` listings.
+pub(crate) fn refresh_floor(nft_contract: Key, limit: u32) {
+    if let Some(scan) = floor_scan(nft_contract) {
+        scan_floor(nft_contract, scan, limit);
+    }
+}
+
+// How `price` for `token_id` changes the cheapest listing, or `Search` when that listing got more
+// expensive.
+fn priced(cheapest: Floor, token_id: &str, price: U256) -> FloorUpdate {
+    match cheapest {
+        Some((cheapest_price, cheapest_token_id)) if cheapest_token_id == token_id => {
+            if price > cheapest_price {
+                FloorUpdate::Search
+            } else {
+                FloorUpdate::Set(Some((price, cheapest_token_id)))
+            }
+        }
+        Some((cheapest_price, _)) if cheapest_price <= price => FloorUpdate::Unchanged,
+        _ => FloorUpdate::Set(Some((price, token_id.to_string()))),
+    }
+}
+
+fn set_floor(nft_contract: Key, floor: Floor) {
+    write_dictionary_value_from_key(
+        COLLECTION_FLOOR,
+        &make_dictionary_item_key_for_key(nft_contract),
+        floor,
+    );
+}
+
+fn floor_scan(nft_contract: Key) -> Option<FloorScan> {
+    get_dictionary_value_from_key::<Option<FloorScan>>(
+        FLOOR_SCANS,
+        &make_dictionary_item_key_for_key(nft_contract),
+    )
+    .flatten()
+}
+
+fn set_floor_scan(nft_contract: Key, scan: Option<FloorScan>) {
+    write_dictionary_value_from_key(
+        FLOOR_SCANS,
+        &make_dictionary_item_key_for_key(nft_contract),
+        scan,
+    );
+}
+
+// Starts searching the whole collection index for the floor, replacing any pending search.
+fn search_floor(nft_contract: Key) {
+    let (len, _) = listing_index::collection_listings(nft_contract, 0, 0);
+    set_floor(nft_contract, None);
+    scan_floor(nft_contract, (len, None), FLOOR_SCAN_LIMIT);
+}
+
+// Reads up to `limit` listings below `next` and sets the floor once the start of the index is
+// reached.
+fn scan_floor(nft_contract: Key, (next, cheapest): FloorScan, limit: u32) {
+    let (len, _) = listing_index::collection_listings(nft_contract, 0, 0);
+    let end = next.min(len);
+    let start = end.saturating_sub(limit);
+    let (_, listings) = listing_index::collection_listings(nft_contract, start, end - start);
+    let cheapest = listings
+        .into_iter()
+        .filter_map(|(_, token_id)| {
+            let market_key = get_token_market_key(&nft_contract, &token_id);
+            get_dictionary_value_from_key::<SellingInMarket>(SELLING_IN_MARKET, &market_key)
+                .filter(|token_market| token_market.is_active)
+                .map(|token_market| (token_market.minimum_offer, token_id))
+        })
+        .chain(cheapest)
+        .min_by(|(a, _), (b, _)| a.cmp(b));
+    if start == 0 {
+        set_floor_scan(nft_contract, None);
+        set_floor(nft_contract, cheapest);
+    } else {
+        set_floor_scan(nft_contract, Some((start, cheapest)));
+    }
+}
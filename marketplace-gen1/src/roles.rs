@@ -0,0 +1,59 @@
+//! Roles of the accounts allowed to administer the marketplace.
+//!
+//! Roles are stored as a bitmask per account in the `roles` dictionary. The contract owner holds
+//! every role without being listed, and so does any account granted `ROLE_ADMIN`.
+use casper_contract::contract_api::runtime;
+use casper_types::Key;
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{
+    self, get_dictionary_value_from_key, get_immediate_caller_key, write_dictionary_value_from_key,
+};
+
+/// Returns the roles granted to `account`.
+pub(crate) fn roles_of(account: Key) -> u8 {
+    get_dictionary_value_from_key::<u8>(ROLES, &helpers::make_dictionary_item_key_for_key(account))
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_roles(account: Key, roles: u8) {
+    write_dictionary_value_from_key(
+        ROLES,
+        &helpers::make_dictionary_item_key_for_key(account),
+        roles,
+    );
+}
+
+/// Returns whether `account` holds `role`.
+pub(crate) fn has_role(account: Key, role: u8) -> bool {
+    let contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    account == contract_owner || roles_of(account) & (role | ROLE_ADMIN) != 0
+}
+
+/// Reverts unless the immediate caller is the contract owner, and returns the caller.
+pub(crate) fn require_owner() -> Key {
+    let caller = get_immediate_caller_key();
+    let contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    if caller != contract_owner {
+        runtime::revert(Error::CallerNotOwner);
+    }
+    caller
+}
+
+/// Reverts unless the immediate caller holds `role`, and returns the caller.
+pub(crate) fn require(role: u8) -> Key {
+    let caller = get_immediate_caller_key();
+    if !has_role(caller, role) {
+        runtime::revert(Error::MissingRole);
+    }
+    caller
+}
@@ -0,0 +1,15 @@
+// Mirrors the values the marketplace contracts write into events.
+
+pub const EVENT_PREFIX: &str = "event_";
+
+pub const SELLING_IN_MARKET_VERSION: u8 = 1;
+
+pub const TRADE_ROUTE_BUY: u8 = 1;
+pub const TRADE_ROUTE_BID: u8 = 2;
+pub const TRADE_ROUTE_ACCEPTED_BID: u8 = 3;
+pub const TRADE_ROUTE_PRICE_CROSSING: u8 = 4;
+
+pub const REFUND_BID_REVOKED: u8 = 1;
+pub const REFUND_BID_EVICTED: u8 = 2;
+pub const REFUND_BID_ON_BUY: u8 = 3;
+pub const REFUND_PRICE_DIFFERENCE: u8 = 4;
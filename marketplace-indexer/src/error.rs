@@ -0,0 +1,52 @@
+use std::fmt;
+
+use casper_types::bytesrepr;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The bytes do not match the layout the contract writes.
+    Bytesrepr(bytesrepr::Error),
+    Hex(hex::FromHexError),
+    /// The event name is not one the marketplace emits.
+    UnknownEvent(String),
+    /// Bytes were left over after decoding a value.
+    TrailingBytes(usize),
+    /// The JSON is not a deploy execution result.
+    MalformedExecutionResult(&'static str),
+    /// Events must be replayed in the order the contract numbered them.
+    OutOfOrder {
+        expected: u32,
+        found: u32,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Bytesrepr(error) => write!(f, "invalid bytes: {}", error),
+            Error::Hex(error) => write!(f, "invalid hex: {}", error),
+            Error::UnknownEvent(name) => write!(f, "unknown event `{}`", name),
+            Error::TrailingBytes(count) => write!(f, "{} trailing bytes", count),
+            Error::MalformedExecutionResult(reason) => {
+                write!(f, "malformed execution result: {}", reason)
+            }
+            Error::OutOfOrder { expected, found } => {
+                write!(f, "expected event {}, found event {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<bytesrepr::Error> for Error {
+    fn from(error: bytesrepr::Error) -> Self {
+        Error::Bytesrepr(error)
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(error: hex::FromHexError) -> Self {
+        Error::Hex(error)
+    }
+}
@@ -0,0 +1,879 @@
+use casper_types::{
+    bytesrepr::{Bytes, FromBytes},
+    Key, U256,
+};
+
+use crate::{constants::EVENT_PREFIX, error::Error};
+
+/// A decoded entry of the contract's `__events` dictionary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventRecord {
+    /// Index of the event in `__events`, counting from zero.
+    pub sequence: u32,
+    /// Block time in milliseconds.
+    pub timestamp: u64,
+    pub event: MarketEvent,
+}
+
+/// Every event the marketplace emits, with fields in the order they are serialized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarketEvent {
+    Sell {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        minimum_offer: U256,
+        is_active: bool,
+    },
+    Revoke {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        minimum_offer: U256,
+        is_active: bool,
+    },
+    DoneSell {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        buyer: Key,
+        value: U256,
+        fee: U256,
+        seller_amount: U256,
+        route: u8,
+    },
+    ChangePrice {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        new_price: U256,
+        is_active: bool,
+    },
+    Bid {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        bidder: Key,
+        value: U256,
+    },
+    RevokeBid {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        bidder: Key,
+        value: U256,
+        is_revoke: bool,
+    },
+    ListingMigrated {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        minimum_offer: U256,
+        target_marketplace: Key,
+        bid_count: u32,
+        bid_total: U256,
+    },
+    ListingImported {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+        minimum_offer: U256,
+        is_active: bool,
+        source_marketplace: Key,
+        bid_count: u32,
+        bid_total: U256,
+    },
+    RoleGranted {
+        account: Key,
+        role: u8,
+        sender: Key,
+    },
+    RoleRevoked {
+        account: Key,
+        role: u8,
+        sender: Key,
+    },
+    OwnershipTransferStarted {
+        previous_owner: Key,
+        new_owner: Key,
+    },
+    OwnershipTransferred {
+        previous_owner: Key,
+        new_owner: Key,
+    },
+    OwnershipTransferCancelled {
+        pending_owner: Key,
+        sender: Key,
+    },
+    ConfigChangeScheduled {
+        config: u8,
+        value: String,
+        effective_at: u64,
+    },
+    ConfigChangeExecuted {
+        config: u8,
+        value: String,
+    },
+    ConfigChangeCancelled {
+        config: u8,
+        value: String,
+    },
+    EmergencyShutdown {
+        sender: Key,
+    },
+    EmergencyNftReturned {
+        nft_contract: Key,
+        token_id: String,
+        offeror: Key,
+    },
+    EmergencyBidRefunded {
+        nft_contract: Key,
+        token_id: String,
+        bidder: Key,
+        value: U256,
+    },
+    Refund {
+        nft_contract: Key,
+        token_id: String,
+        recipient: Key,
+        value: U256,
+        reason: u8,
+    },
+    CollectionUpdated {
+        nft_contract: Key,
+        nft_standard: u8,
+    },
+    PauseUpdated {
+        nft_contract: Option<Key>,
+        paused: u8,
+    },
+    BidConfigUpdated {
+        nft_contract: Key,
+        min_bid: U256,
+        min_bid_increment: U256,
+        min_bid_increment_bps: U256,
+    },
+    MaxBidsPerTokenUpdated {
+        max_bids_per_token: u32,
+    },
+    MigrationSourceUpdated {
+        source_marketplace: Key,
+        enabled: bool,
+    },
+    ContractUpgraded {
+        contract_version: u32,
+    },
+    ReferralPaid {
+        nft_contract: Key,
+        token_id: String,
+        referrer: Key,
+        value: U256,
+    },
+    ReferralShareUpdated {
+        referral_share_bps: U256,
+    },
+    FeeOverrideUpdated {
+        nft_contract: Option<Key>,
+        route: u8,
+        fee_bps: Option<U256>,
+    },
+    MigrationTargetCleared {
+        target_marketplace: Key,
+    },
+}
+
+// Reads values one after another, keeping track of the unread bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read<T: FromBytes>(&mut self) -> Result<T, Error> {
+        let (value, remainder) = T::from_bytes(self.bytes)?;
+        self.bytes = remainder;
+        Ok(value)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TrailingBytes(self.bytes.len()))
+        }
+    }
+}
+
+impl EventRecord {
+    /// Decodes the payload of a `__events` entry, without the `Bytes` length prefix.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { bytes };
+        let name: String = reader.read()?;
+        let sequence: u32 = reader.read()?;
+        let timestamp: u64 = reader.read()?;
+        let event = match name.strip_prefix(EVENT_PREFIX) {
+            Some(name) => MarketEvent::read(name, &mut reader)?,
+            None => return Err(Error::UnknownEvent(name)),
+        };
+        reader.finish()?;
+        Ok(EventRecord {
+            sequence,
+            timestamp,
+            event,
+        })
+    }
+
+    /// Decodes a `__events` entry as stored, a `Bytes` value holding the payload.
+    pub fn from_stored_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { bytes };
+        let payload: Bytes = reader.read()?;
+        reader.finish()?;
+        EventRecord::from_bytes(&payload)
+    }
+
+    /// Same as [`EventRecord::from_stored_bytes`], for the hex the node returns.
+    pub fn from_stored_hex(hex_bytes: &str) -> Result<Self, Error> {
+        EventRecord::from_stored_bytes(&hex::decode(hex_bytes)?)
+    }
+}
+
+impl MarketEvent {
+    /// The event name without the `event_` prefix, as listed in the contract's schema.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarketEvent::Sell { .. } => "Sell",
+            MarketEvent::Revoke { .. } => "Revoke",
+            MarketEvent::DoneSell { .. } => "DoneSell",
+            MarketEvent::ChangePrice { .. } => "ChangePrice",
+            MarketEvent::Bid { .. } => "Bid",
+            MarketEvent::RevokeBid { .. } => "RevokeBid",
+            MarketEvent::ListingMigrated { .. } => "ListingMigrated",
+            MarketEvent::ListingImported { .. } => "ListingImported",
+            MarketEvent::RoleGranted { .. } => "RoleGranted",
+            MarketEvent::RoleRevoked { .. } => "RoleRevoked",
+            MarketEvent::OwnershipTransferStarted { .. } => "OwnershipTransferStarted",
+            MarketEvent::OwnershipTransferred { .. } => "OwnershipTransferred",
+            MarketEvent::OwnershipTransferCancelled { .. } => "OwnershipTransferCancelled",
+            MarketEvent::ConfigChangeScheduled { .. } => "ConfigChangeScheduled",
+            MarketEvent::ConfigChangeExecuted { .. } => "ConfigChangeExecuted",
+            MarketEvent::ConfigChangeCancelled { .. } => "ConfigChangeCancelled",
+            MarketEvent::EmergencyShutdown { .. } => "EmergencyShutdown",
+            MarketEvent::EmergencyNftReturned { .. } => "EmergencyNftReturned",
+            MarketEvent::EmergencyBidRefunded { .. } => "EmergencyBidRefunded",
+            MarketEvent::Refund { .. } => "Refund",
+            MarketEvent::CollectionUpdated { .. } => "CollectionUpdated",
+            MarketEvent::PauseUpdated { .. } => "PauseUpdated",
+            MarketEvent::BidConfigUpdated { .. } => "BidConfigUpdated",
+            MarketEvent::MaxBidsPerTokenUpdated { .. } => "MaxBidsPerTokenUpdated",
+            MarketEvent::MigrationSourceUpdated { .. } => "MigrationSourceUpdated",
+            MarketEvent::ContractUpgraded { .. } => "ContractUpgraded",
+            MarketEvent::ReferralPaid { .. } => "ReferralPaid",
+            MarketEvent::ReferralShareUpdated { .. } => "ReferralShareUpdated",
+            MarketEvent::FeeOverrideUpdated { .. } => "FeeOverrideUpdated",
+            MarketEvent::MigrationTargetCleared { .. } => "MigrationTargetCleared",
+        }
+    }
+
+    fn read(name: &str, reader: &mut Reader) -> Result<Self, Error> {
+        let event = match name {
+            "Sell" => MarketEvent::Sell {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                minimum_offer: reader.read()?,
+                is_active: reader.read()?,
+            },
+            "Revoke" => MarketEvent::Revoke {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                minimum_offer: reader.read()?,
+                is_active: reader.read()?,
+            },
+            "DoneSell" => MarketEvent::DoneSell {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                buyer: reader.read()?,
+                value: reader.read()?,
+                fee: reader.read()?,
+                seller_amount: reader.read()?,
+                route: reader.read()?,
+            },
+            "ChangePrice" => MarketEvent::ChangePrice {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                new_price: reader.read()?,
+                is_active: reader.read()?,
+            },
+            "Bid" => MarketEvent::Bid {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                bidder: reader.read()?,
+                value: reader.read()?,
+            },
+            "RevokeBid" => MarketEvent::RevokeBid {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                bidder: reader.read()?,
+                value: reader.read()?,
+                is_revoke: reader.read()?,
+            },
+            "ListingMigrated" => MarketEvent::ListingMigrated {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                minimum_offer: reader.read()?,
+                target_marketplace: reader.read()?,
+                bid_count: reader.read()?,
+                bid_total: reader.read()?,
+            },
+            "ListingImported" => MarketEvent::ListingImported {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+                minimum_offer: reader.read()?,
+                is_active: reader.read()?,
+                source_marketplace: reader.read()?,
+                bid_count: reader.read()?,
+                bid_total: reader.read()?,
+            },
+            "RoleGranted" => MarketEvent::RoleGranted {
+                account: reader.read()?,
+                role: reader.read()?,
+                sender: reader.read()?,
+            },
+            "RoleRevoked" => MarketEvent::RoleRevoked {
+                account: reader.read()?,
+                role: reader.read()?,
+                sender: reader.read()?,
+            },
+            "OwnershipTransferStarted" => MarketEvent::OwnershipTransferStarted {
+                previous_owner: reader.read()?,
+                new_owner: reader.read()?,
+            },
+            "OwnershipTransferred" => MarketEvent::OwnershipTransferred {
+                previous_owner: reader.read()?,
+                new_owner: reader.read()?,
+            },
+            "OwnershipTransferCancelled" => MarketEvent::OwnershipTransferCancelled {
+                pending_owner: reader.read()?,
+                sender: reader.read()?,
+            },
+            "ConfigChangeScheduled" => MarketEvent::ConfigChangeScheduled {
+                config: reader.read()?,
+                value: reader.read()?,
+                effective_at: reader.read()?,
+            },
+            "ConfigChangeExecuted" => MarketEvent::ConfigChangeExecuted {
+                config: reader.read()?,
+                value: reader.read()?,
+            },
+            "ConfigChangeCancelled" => MarketEvent::ConfigChangeCancelled {
+                config: reader.read()?,
+                value: reader.read()?,
+            },
+            "EmergencyShutdown" => MarketEvent::EmergencyShutdown {
+                sender: reader.read()?,
+            },
+            "EmergencyNftReturned" => MarketEvent::EmergencyNftReturned {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                offeror: reader.read()?,
+            },
+            "EmergencyBidRefunded" => MarketEvent::EmergencyBidRefunded {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                bidder: reader.read()?,
+                value: reader.read()?,
+            },
+            "Refund" => MarketEvent::Refund {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                recipient: reader.read()?,
+                value: reader.read()?,
+                reason: reader.read()?,
+            },
+            "CollectionUpdated" => MarketEvent::CollectionUpdated {
+                nft_contract: reader.read()?,
+                nft_standard: reader.read()?,
+            },
+            "PauseUpdated" => MarketEvent::PauseUpdated {
+                nft_contract: reader.read()?,
+                paused: reader.read()?,
+            },
+            "BidConfigUpdated" => MarketEvent::BidConfigUpdated {
+                nft_contract: reader.read()?,
+                min_bid: reader.read()?,
+                min_bid_increment: reader.read()?,
+                min_bid_increment_bps: reader.read()?,
+            },
+            "MaxBidsPerTokenUpdated" => MarketEvent::MaxBidsPerTokenUpdated {
+                max_bids_per_token: reader.read()?,
+            },
+            "MigrationSourceUpdated" => MarketEvent::MigrationSourceUpdated {
+                source_marketplace: reader.read()?,
+                enabled: reader.read()?,
+            },
+            "ContractUpgraded" => MarketEvent::ContractUpgraded {
+                contract_version: reader.read()?,
+            },
+            "ReferralPaid" => MarketEvent::ReferralPaid {
+                nft_contract: reader.read()?,
+                token_id: reader.read()?,
+                referrer: reader.read()?,
+                value: reader.read()?,
+            },
+            "ReferralShareUpdated" => MarketEvent::ReferralShareUpdated {
+                referral_share_bps: reader.read()?,
+            },
+            "FeeOverrideUpdated" => MarketEvent::FeeOverrideUpdated {
+                nft_contract: reader.read()?,
+                route: reader.read()?,
+                fee_bps: reader.read()?,
+            },
+            "MigrationTargetCleared" => MarketEvent::MigrationTargetCleared {
+                target_marketplace: reader.read()?,
+            },
+            _ => return Err(Error::UnknownEvent(name.to_string())),
+        };
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{account::AccountHash, bytesrepr::ToBytes};
+
+    use super::*;
+
+    fn key(byte: u8) -> Key {
+        if byte % 2 == 0 {
+            Key::Account(AccountHash::new([byte; 32]))
+        } else {
+            Key::Hash([byte; 32])
+        }
+    }
+
+    fn to_bytes<T: ToBytes>(value: T) -> Vec<u8> {
+        value.to_bytes().unwrap()
+    }
+
+    // Lays an event out the way `MarketPlaceEvent::to_bytes` does in the contracts.
+    fn contract_payload(name: &str, sequence: u32, timestamp: u64, fields: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = to_bytes(EVENT_PREFIX.to_string() + name);
+        bytes.extend(to_bytes(sequence));
+        bytes.extend(to_bytes(timestamp));
+        for field in fields {
+            bytes.extend(field);
+        }
+        bytes
+    }
+
+    // One of every event, with the fields as the contract appends them.
+    fn cases() -> Vec<(MarketEvent, Vec<Vec<u8>>)> {
+        vec![
+            (
+                MarketEvent::Sell {
+                    nft_contract: key(1),
+                    token_id: "2".to_string(),
+                    offeror: key(3),
+                    minimum_offer: U256::from(4u64),
+                    is_active: true,
+                },
+                vec![
+                    to_bytes(key(1)),
+                    to_bytes("2".to_string()),
+                    to_bytes(key(3)),
+                    to_bytes(U256::from(4u64)),
+                    to_bytes(true),
+                ],
+            ),
+            (
+                MarketEvent::Revoke {
+                    nft_contract: key(6),
+                    token_id: "7".to_string(),
+                    offeror: key(8),
+                    minimum_offer: U256::from(9u64),
+                    is_active: true,
+                },
+                vec![
+                    to_bytes(key(6)),
+                    to_bytes("7".to_string()),
+                    to_bytes(key(8)),
+                    to_bytes(U256::from(9u64)),
+                    to_bytes(true),
+                ],
+            ),
+            (
+                MarketEvent::DoneSell {
+                    nft_contract: key(11),
+                    token_id: "12".to_string(),
+                    offeror: key(13),
+                    buyer: key(14),
+                    value: U256::from(15u64),
+                    fee: U256::from(16u64),
+                    seller_amount: U256::from(17u64),
+                    route: 18u8,
+                },
+                vec![
+                    to_bytes(key(11)),
+                    to_bytes("12".to_string()),
+                    to_bytes(key(13)),
+                    to_bytes(key(14)),
+                    to_bytes(U256::from(15u64)),
+                    to_bytes(U256::from(16u64)),
+                    to_bytes(U256::from(17u64)),
+                    to_bytes(18u8),
+                ],
+            ),
+            (
+                MarketEvent::ChangePrice {
+                    nft_contract: key(19),
+                    token_id: "20".to_string(),
+                    offeror: key(21),
+                    new_price: U256::from(22u64),
+                    is_active: true,
+                },
+                vec![
+                    to_bytes(key(19)),
+                    to_bytes("20".to_string()),
+                    to_bytes(key(21)),
+                    to_bytes(U256::from(22u64)),
+                    to_bytes(true),
+                ],
+            ),
+            (
+                MarketEvent::Bid {
+                    nft_contract: key(24),
+                    token_id: "25".to_string(),
+                    offeror: key(26),
+                    bidder: key(27),
+                    value: U256::from(28u64),
+                },
+                vec![
+                    to_bytes(key(24)),
+                    to_bytes("25".to_string()),
+                    to_bytes(key(26)),
+                    to_bytes(key(27)),
+                    to_bytes(U256::from(28u64)),
+                ],
+            ),
+            (
+                MarketEvent::RevokeBid {
+                    nft_contract: key(29),
+                    token_id: "30".to_string(),
+                    offeror: key(31),
+                    bidder: key(32),
+                    value: U256::from(33u64),
+                    is_revoke: true,
+                },
+                vec![
+                    to_bytes(key(29)),
+                    to_bytes("30".to_string()),
+                    to_bytes(key(31)),
+                    to_bytes(key(32)),
+                    to_bytes(U256::from(33u64)),
+                    to_bytes(true),
+                ],
+            ),
+            (
+                MarketEvent::ListingMigrated {
+                    nft_contract: key(35),
+                    token_id: "36".to_string(),
+                    offeror: key(37),
+                    minimum_offer: U256::from(38u64),
+                    target_marketplace: key(39),
+                    bid_count: 40u32,
+                    bid_total: U256::from(41u64),
+                },
+                vec![
+                    to_bytes(key(35)),
+                    to_bytes("36".to_string()),
+                    to_bytes(key(37)),
+                    to_bytes(U256::from(38u64)),
+                    to_bytes(key(39)),
+                    to_bytes(40u32),
+                    to_bytes(U256::from(41u64)),
+                ],
+            ),
+            (
+                MarketEvent::ListingImported {
+                    nft_contract: key(42),
+                    token_id: "43".to_string(),
+                    offeror: key(44),
+                    minimum_offer: U256::from(45u64),
+                    is_active: true,
+                    source_marketplace: key(47),
+                    bid_count: 48u32,
+                    bid_total: U256::from(49u64),
+                },
+                vec![
+                    to_bytes(key(42)),
+                    to_bytes("43".to_string()),
+                    to_bytes(key(44)),
+                    to_bytes(U256::from(45u64)),
+                    to_bytes(true),
+                    to_bytes(key(47)),
+                    to_bytes(48u32),
+                    to_bytes(U256::from(49u64)),
+                ],
+            ),
+            (
+                MarketEvent::RoleGranted {
+                    account: key(50),
+                    role: 51u8,
+                    sender: key(52),
+                },
+                vec![to_bytes(key(50)), to_bytes(51u8), to_bytes(key(52))],
+            ),
+            (
+                MarketEvent::RoleRevoked {
+                    account: key(53),
+                    role: 54u8,
+                    sender: key(55),
+                },
+                vec![to_bytes(key(53)), to_bytes(54u8), to_bytes(key(55))],
+            ),
+            (
+                MarketEvent::OwnershipTransferStarted {
+                    previous_owner: key(56),
+                    new_owner: key(57),
+                },
+                vec![to_bytes(key(56)), to_bytes(key(57))],
+            ),
+            (
+                MarketEvent::OwnershipTransferred {
+                    previous_owner: key(58),
+                    new_owner: key(59),
+                },
+                vec![to_bytes(key(58)), to_bytes(key(59))],
+            ),
+            (
+                MarketEvent::OwnershipTransferCancelled {
+                    pending_owner: key(60),
+                    sender: key(61),
+                },
+                vec![to_bytes(key(60)), to_bytes(key(61))],
+            ),
+            (
+                MarketEvent::ConfigChangeScheduled {
+                    config: 62u8,
+                    value: "63".to_string(),
+                    effective_at: 64u64,
+                },
+                vec![to_bytes(62u8), to_bytes("63".to_string()), to_bytes(64u64)],
+            ),
+            (
+                MarketEvent::ConfigChangeExecuted {
+                    config: 65u8,
+                    value: "66".to_string(),
+                },
+                vec![to_bytes(65u8), to_bytes("66".to_string())],
+            ),
+            (
+                MarketEvent::ConfigChangeCancelled {
+                    config: 67u8,
+                    value: "68".to_string(),
+                },
+                vec![to_bytes(67u8), to_bytes("68".to_string())],
+            ),
+            (
+                MarketEvent::EmergencyShutdown { sender: key(69) },
+                vec![to_bytes(key(69))],
+            ),
+            (
+                MarketEvent::EmergencyNftReturned {
+                    nft_contract: key(70),
+                    token_id: "71".to_string(),
+                    offeror: key(72),
+                },
+                vec![
+                    to_bytes(key(70)),
+                    to_bytes("71".to_string()),
+                    to_bytes(key(72)),
+                ],
+            ),
+            (
+                MarketEvent::EmergencyBidRefunded {
+                    nft_contract: key(73),
+                    token_id: "74".to_string(),
+                    bidder: key(75),
+                    value: U256::from(76u64),
+                },
+                vec![
+                    to_bytes(key(73)),
+                    to_bytes("74".to_string()),
+                    to_bytes(key(75)),
+                    to_bytes(U256::from(76u64)),
+                ],
+            ),
+            (
+                MarketEvent::Refund {
+                    nft_contract: key(77),
+                    token_id: "78".to_string(),
+                    recipient: key(79),
+                    value: U256::from(80u64),
+                    reason: 81u8,
+                },
+                vec![
+                    to_bytes(key(77)),
+                    to_bytes("78".to_string()),
+                    to_bytes(key(79)),
+                    to_bytes(U256::from(80u64)),
+                    to_bytes(81u8),
+                ],
+            ),
+            (
+                MarketEvent::CollectionUpdated {
+                    nft_contract: key(82),
+                    nft_standard: 83u8,
+                },
+                vec![to_bytes(key(82)), to_bytes(83u8)],
+            ),
+            (
+                MarketEvent::PauseUpdated {
+                    nft_contract: Some(key(84)),
+                    paused: 85u8,
+                },
+                vec![to_bytes(Some(key(84))), to_bytes(85u8)],
+            ),
+            (
+                MarketEvent::BidConfigUpdated {
+                    nft_contract: key(86),
+                    min_bid: U256::from(87u64),
+                    min_bid_increment: U256::from(88u64),
+                    min_bid_increment_bps: U256::from(89u64),
+                },
+                vec![
+                    to_bytes(key(86)),
+                    to_bytes(U256::from(87u64)),
+                    to_bytes(U256::from(88u64)),
+                    to_bytes(U256::from(89u64)),
+                ],
+            ),
+            (
+                MarketEvent::MaxBidsPerTokenUpdated {
+                    max_bids_per_token: 90u32,
+                },
+                vec![to_bytes(90u32)],
+            ),
+            (
+                MarketEvent::MigrationSourceUpdated {
+                    source_marketplace: key(91),
+                    enabled: true,
+                },
+                vec![to_bytes(key(91)), to_bytes(true)],
+            ),
+            (
+                MarketEvent::ContractUpgraded {
+                    contract_version: 93u32,
+                },
+                vec![to_bytes(93u32)],
+            ),
+            (
+                MarketEvent::ReferralPaid {
+                    nft_contract: key(94),
+                    token_id: "95".to_string(),
+                    referrer: key(96),
+                    value: U256::from(97u64),
+                },
+                vec![
+                    to_bytes(key(94)),
+                    to_bytes("95".to_string()),
+                    to_bytes(key(96)),
+                    to_bytes(U256::from(97u64)),
+                ],
+            ),
+            (
+                MarketEvent::ReferralShareUpdated {
+                    referral_share_bps: U256::from(98u64),
+                },
+                vec![to_bytes(U256::from(98u64))],
+            ),
+            (
+                MarketEvent::FeeOverrideUpdated {
+                    nft_contract: Some(key(99)),
+                    route: 100u8,
+                    fee_bps: Some(U256::from(101u64)),
+                },
+                vec![
+                    to_bytes(Some(key(99))),
+                    to_bytes(100u8),
+                    to_bytes(Some(U256::from(101u64))),
+                ],
+            ),
+            (
+                MarketEvent::MigrationTargetCleared {
+                    target_marketplace: key(102),
+                },
+                vec![to_bytes(key(102))],
+            ),
+            (
+                MarketEvent::FeeOverrideUpdated {
+                    nft_contract: None,
+                    route: 1,
+                    fee_bps: None,
+                },
+                vec![to_bytes(None::<Key>), to_bytes(1u8), to_bytes(None::<U256>)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn decodes_every_event_from_the_contract_layout() {
+        for (sequence, (event, fields)) in cases().into_iter().enumerate() {
+            let sequence = sequence as u32;
+            let payload = contract_payload(event.name(), sequence, 1_700_000_000_000, &fields);
+            let expected = EventRecord {
+                sequence,
+                timestamp: 1_700_000_000_000,
+                event,
+            };
+            assert_eq!(EventRecord::from_bytes(&payload).unwrap(), expected);
+
+            let stored = to_bytes(Bytes::from(payload));
+            assert_eq!(EventRecord::from_stored_bytes(&stored).unwrap(), expected);
+            assert_eq!(
+                EventRecord::from_stored_hex(&hex::encode(&stored)).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_events() {
+        let payload = contract_payload("Transfer", 0, 0, &[]);
+        assert!(matches!(
+            EventRecord::from_bytes(&payload),
+            Err(Error::UnknownEvent(name)) if name == "Transfer"
+        ));
+
+        let mut payload = to_bytes("Sell".to_string());
+        payload.extend(to_bytes(0u32));
+        payload.extend(to_bytes(0u64));
+        assert!(matches!(
+            EventRecord::from_bytes(&payload),
+            Err(Error::UnknownEvent(name)) if name == "Sell"
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_and_trailing_bytes() {
+        let (event, fields) = cases().remove(0);
+        let mut payload = contract_payload(event.name(), 0, 0, &fields);
+
+        assert!(matches!(
+            EventRecord::from_bytes(&payload[..payload.len() - 1]),
+            Err(Error::Bytesrepr(_))
+        ));
+
+        payload.push(0);
+        assert!(matches!(
+            EventRecord::from_bytes(&payload),
+            Err(Error::TrailingBytes(1))
+        ));
+    }
+}
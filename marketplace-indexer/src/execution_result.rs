@@ -0,0 +1,218 @@
+use casper_types::{
+    bytesrepr::{Bytes, FromBytes},
+    CLType, CLValue, URef,
+};
+use serde_json::Value;
+
+use crate::{constants::EVENT_PREFIX, error::Error, event::EventRecord};
+
+/// Collects the events written by a deploy, ordered by sequence.
+///
+/// `execution_result` is one entry of `execution_results` from `info_get_deploy`, or the
+/// `ExecutionResult` inside it. A deploy that reaches several marketplaces writes to each one's
+/// `__events`; pass that marketplace's `events_uref` to keep only its events. Without it, the
+/// writes of other contracts following the event standard are mixed in, and events with a name the
+/// marketplace does not emit are skipped. Failed deploys yield no events.
+pub fn events_from_execution_result(
+    execution_result: &Value,
+    events_uref: Option<URef>,
+) -> Result<Vec<EventRecord>, Error> {
+    let execution_result = execution_result.get("result").unwrap_or(execution_result);
+    let success = match execution_result.get("Success") {
+        Some(success) => success,
+        None if execution_result.get("Failure").is_some() => return Ok(Vec::new()),
+        None => {
+            return Err(Error::MalformedExecutionResult(
+                "expected `Success` or `Failure`",
+            ))
+        }
+    };
+    let transforms = success
+        .get("effect")
+        .and_then(|effect| effect.get("transforms"))
+        .and_then(Value::as_array)
+        .ok_or(Error::MalformedExecutionResult(
+            "missing `effect.transforms`",
+        ))?;
+
+    let mut events = Vec::new();
+    for entry in transforms {
+        let is_dictionary = entry
+            .get("key")
+            .and_then(Value::as_str)
+            .map_or(false, |key| key.starts_with("dictionary-"));
+        let write = entry
+            .get("transform")
+            .and_then(|transform| transform.get("WriteCLValue"));
+        let hex_bytes = match write.and_then(|write| write.get("bytes")) {
+            Some(Value::String(hex_bytes)) if is_dictionary => hex_bytes,
+            _ => continue,
+        };
+        if let Some(event) = event_from_dictionary_write(&hex::decode(hex_bytes)?, events_uref)? {
+            events.push(event);
+        }
+    }
+    events.sort_by_key(|event| event.sequence);
+    Ok(events)
+}
+
+// A dictionary write stores the value together with the dictionary's seed URef address and the
+// item key. Writes to other dictionaries are skipped.
+fn event_from_dictionary_write(
+    bytes: &[u8],
+    events_uref: Option<URef>,
+) -> Result<Option<EventRecord>, Error> {
+    let (cl_value, remainder) = CLValue::from_bytes(bytes)?;
+    let (seed_uref_addr, _remainder) = Bytes::from_bytes(remainder)?;
+    if let Some(events_uref) = events_uref {
+        if seed_uref_addr.as_slice() != events_uref.addr().as_slice() {
+            return Ok(None);
+        }
+    }
+    if *cl_value.cl_type() != CLType::List(Box::new(CLType::U8)) {
+        return Ok(None);
+    }
+
+    let (payload, _remainder) = Bytes::from_bytes(cl_value.inner_bytes())?;
+    match String::from_bytes(&payload) {
+        Ok((name, _remainder)) if name.starts_with(EVENT_PREFIX) => {
+            match EventRecord::from_bytes(&payload) {
+                Ok(event) => Ok(Some(event)),
+                Err(Error::UnknownEvent(_)) if events_uref.is_none() => Ok(None),
+                Err(error) => Err(error),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{
+        account::AccountHash,
+        bytesrepr::{Bytes, ToBytes},
+        AccessRights, Key, U256,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::event::MarketEvent;
+
+    fn events_uref() -> URef {
+        URef::new([9; 32], AccessRights::READ_ADD_WRITE)
+    }
+
+    fn other_uref() -> URef {
+        URef::new([8; 32], AccessRights::READ_ADD_WRITE)
+    }
+
+    fn to_bytes<T: ToBytes>(value: T) -> Vec<u8> {
+        value.to_bytes().unwrap()
+    }
+
+    fn event_payload(name: &str, sequence: u32, fields: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = to_bytes(EVENT_PREFIX.to_string() + name);
+        bytes.extend(to_bytes(sequence));
+        bytes.extend(to_bytes(1_700_000_000_000u64));
+        for field in fields {
+            bytes.extend(field);
+        }
+        bytes
+    }
+
+    fn max_bids_payload(sequence: u32) -> Vec<u8> {
+        event_payload("MaxBidsPerTokenUpdated", sequence, &[to_bytes(sequence)])
+    }
+
+    // A dictionary write as it shows up in the transforms: the value, the seed URef address and
+    // the item key.
+    fn dictionary_write(seed_uref: URef, item_key: &str, value: CLValue) -> serde_json::Value {
+        let mut bytes = to_bytes(value);
+        bytes.extend(to_bytes(Bytes::from(seed_uref.addr().to_vec())));
+        bytes.extend(to_bytes(Bytes::from(item_key.as_bytes().to_vec())));
+        json!({
+            "key": format!("dictionary-{}", hex::encode([seed_uref.addr()[0]; 32])),
+            "transform": { "WriteCLValue": { "cl_type": "Any", "bytes": hex::encode(bytes), "parsed": null } }
+        })
+    }
+
+    fn event_write(seed_uref: URef, payload: Vec<u8>) -> serde_json::Value {
+        dictionary_write(
+            seed_uref,
+            "0",
+            CLValue::from_t(Bytes::from(payload)).unwrap(),
+        )
+    }
+
+    fn success(transforms: Vec<serde_json::Value>) -> serde_json::Value {
+        json!({ "block_hash": "00", "result": { "Success": { "effect": { "transforms": transforms } } } })
+    }
+
+    #[test]
+    fn collects_the_marketplace_events_in_sequence_order() {
+        let execution_result = success(vec![
+            event_write(events_uref(), max_bids_payload(5)),
+            json!({ "key": "uref-00", "transform": "Identity" }),
+            dictionary_write(
+                events_uref(),
+                "1",
+                CLValue::from_t(U256::from(1u64)).unwrap(),
+            ),
+            event_write(other_uref(), max_bids_payload(9)),
+            event_write(events_uref(), max_bids_payload(4)),
+        ]);
+
+        let events = events_from_execution_result(&execution_result, Some(events_uref())).unwrap();
+        let sequences: Vec<u32> = events.iter().map(|event| event.sequence).collect();
+        assert_eq!(sequences, vec![4, 5]);
+        assert_eq!(
+            events[0].event,
+            MarketEvent::MaxBidsPerTokenUpdated {
+                max_bids_per_token: 4
+            }
+        );
+
+        let events = events_from_execution_result(&execution_result, None).unwrap();
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn skips_events_of_other_contracts_without_events_uref() {
+        let owner = Key::Account(AccountHash::new([2; 32]));
+        let transfer = event_payload("Transfer", 0, &[to_bytes(owner)]);
+        let execution_result = success(vec![
+            event_write(other_uref(), transfer.clone()),
+            event_write(events_uref(), max_bids_payload(0)),
+        ]);
+
+        let events = events_from_execution_result(&execution_result, None).unwrap();
+        assert_eq!(events.len(), 1);
+
+        // an unknown event in the marketplace's own dictionary is still an error
+        let execution_result = success(vec![event_write(events_uref(), transfer)]);
+        assert!(matches!(
+            events_from_execution_result(&execution_result, Some(events_uref())),
+            Err(Error::UnknownEvent(name)) if name == "Transfer"
+        ));
+    }
+
+    #[test]
+    fn failed_deploys_have_no_events() {
+        let execution_result = json!({ "Failure": { "effect": { "transforms": [] }, "error_message": "User error: 1" } });
+        assert!(events_from_execution_result(&execution_result, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_execution_results() {
+        assert!(matches!(
+            events_from_execution_result(&json!({ "Success": {} }), None),
+            Err(Error::MalformedExecutionResult(_))
+        ));
+        assert!(matches!(
+            events_from_execution_result(&json!({}), None),
+            Err(Error::MalformedExecutionResult(_))
+        ));
+    }
+}
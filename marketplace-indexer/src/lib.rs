@@ -0,0 +1,18 @@
+//! Host-side decoder for marketplace contract data.
+//!
+//! Decodes the CES events emitted into the `__events` dictionary and the `selling_in_market`
+//! records, either from raw bytes or from the JSON execution results of a deploy, and replays the
+//! event stream into an in-memory [`MarketState`].
+
+pub mod constants;
+pub mod error;
+pub mod event;
+pub mod execution_result;
+pub mod listing;
+pub mod state;
+
+pub use error::Error;
+pub use event::{EventRecord, MarketEvent};
+pub use execution_result::events_from_execution_result;
+pub use listing::{bid_book_from_bytes, bid_book_from_hex, BidBook, SellingInMarket};
+pub use state::{Listing, MarketState, Sale, TokenRef};
@@ -0,0 +1,205 @@
+use casper_types::{
+    bytesrepr::{self, FromBytes},
+    Key, U256,
+};
+
+use crate::{constants::SELLING_IN_MARKET_VERSION, error::Error};
+
+/// Bids on a listing. The `bid_book` dictionary only keeps the highest ones, lowest first; every
+/// bid is listed in the `bid_list` dictionary with its price in `bids`.
+pub type BidBook = Vec<(Key, U256)>;
+
+/// Decodes a `bid_book` dictionary value, the top of the listing's book.
+pub fn bid_book_from_bytes(bytes: &[u8]) -> Result<BidBook, Error> {
+    let (bid_book, remainder) = BidBook::from_bytes(bytes)?;
+    if !remainder.is_empty() {
+        return Err(Error::TrailingBytes(remainder.len()));
+    }
+    Ok(bid_book)
+}
+
+/// Same as [`bid_book_from_bytes`], for the hex the node returns.
+pub fn bid_book_from_hex(hex_bytes: &str) -> Result<BidBook, Error> {
+    bid_book_from_bytes(&hex::decode(hex_bytes)?)
+}
+
+/// A `selling_in_market` dictionary record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SellingInMarket {
+    pub token_id: String,
+    pub nft_contract: Key,
+    pub offeror: Option<Key>,
+    pub minimum_offer: U256,
+    pub is_active: bool,
+    /// Bids still held in a record written before the bid book; empty for versioned records.
+    pub legacy_bids: Vec<(Key, U256)>,
+}
+
+type SellingInMarketV1 = (u8, (String, Key, Option<Key>), (U256, bool));
+
+impl SellingInMarket {
+    /// Decodes a record from its stored bytes, in either the versioned or the legacy layout.
+    pub fn from_record_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (record, remainder) = SellingInMarket::from_bytes(bytes)?;
+        if !remainder.is_empty() {
+            return Err(Error::TrailingBytes(remainder.len()));
+        }
+        Ok(record)
+    }
+
+    /// Same as [`SellingInMarket::from_record_bytes`], for the hex the node returns.
+    pub fn from_record_hex(hex_bytes: &str) -> Result<Self, Error> {
+        SellingInMarket::from_record_bytes(&hex::decode(hex_bytes)?)
+    }
+
+    fn from_legacy_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (token_id, remainder) = String::from_bytes(bytes)?;
+        let (nft_contract, remainder) = Key::from_bytes(remainder)?;
+        let (offeror, remainder) = Option::<Key>::from_bytes(remainder)?;
+        let (minimum_offer, remainder) = U256::from_bytes(remainder)?;
+        let (is_active, remainder) = bool::from_bytes(remainder)?;
+        let (bidder, remainder) = Vec::<Key>::from_bytes(remainder)?;
+        let (bidding_price, remainder) = Vec::<U256>::from_bytes(remainder)?;
+
+        if bidder.len() != bidding_price.len() {
+            return Err(bytesrepr::Error::Formatting);
+        }
+
+        let ret = SellingInMarket {
+            token_id,
+            nft_contract,
+            offeror,
+            minimum_offer,
+            is_active,
+            legacy_bids: bidder.into_iter().zip(bidding_price).collect(),
+        };
+        Ok((ret, remainder))
+    }
+}
+
+impl FromBytes for SellingInMarket {
+    // Same dispatch as the contract: a legacy record never decodes as a versioned one.
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        if bytes.first() == Some(&SELLING_IN_MARKET_VERSION) {
+            if let Ok((record, remainder)) = SellingInMarketV1::from_bytes(bytes) {
+                let (_version, (token_id, nft_contract, offeror), (minimum_offer, is_active)) =
+                    record;
+                let ret = SellingInMarket {
+                    token_id,
+                    nft_contract,
+                    offeror,
+                    minimum_offer,
+                    is_active,
+                    legacy_bids: Vec::new(),
+                };
+                return Ok((ret, remainder));
+            }
+        }
+        SellingInMarket::from_legacy_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{account::AccountHash, bytesrepr::ToBytes};
+
+    use super::*;
+
+    fn seller() -> Key {
+        Key::Account(AccountHash::new([2; 32]))
+    }
+
+    fn bidder(byte: u8) -> Key {
+        Key::Account(AccountHash::new([byte; 32]))
+    }
+
+    fn nft_contract() -> Key {
+        Key::Hash([1; 32])
+    }
+
+    // Fields of a record written before versioning, bids as two parallel lists.
+    fn legacy_bytes(bidders: &[Key], prices: &[U256]) -> Vec<u8> {
+        let mut bytes = "7".to_string().to_bytes().unwrap();
+        bytes.extend(nft_contract().to_bytes().unwrap());
+        bytes.extend(Some(seller()).to_bytes().unwrap());
+        bytes.extend(U256::from(500u64).to_bytes().unwrap());
+        bytes.extend(true.to_bytes().unwrap());
+        bytes.extend(bidders.to_vec().to_bytes().unwrap());
+        bytes.extend(prices.to_vec().to_bytes().unwrap());
+        bytes
+    }
+
+    #[test]
+    fn decodes_versioned_records() {
+        let record: SellingInMarketV1 = (
+            SELLING_IN_MARKET_VERSION,
+            ("7".to_string(), nft_contract(), Some(seller())),
+            (U256::from(500u64), true),
+        );
+        let bytes = record.to_bytes().unwrap();
+
+        let expected = SellingInMarket {
+            token_id: "7".to_string(),
+            nft_contract: nft_contract(),
+            offeror: Some(seller()),
+            minimum_offer: U256::from(500u64),
+            is_active: true,
+            legacy_bids: Vec::new(),
+        };
+        assert_eq!(
+            SellingInMarket::from_record_bytes(&bytes).unwrap(),
+            expected
+        );
+        assert_eq!(
+            SellingInMarket::from_record_hex(&hex::encode(&bytes)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn decodes_legacy_records_with_their_bids() {
+        let bidders = [bidder(3), bidder(4)];
+        let prices = [U256::from(100u64), U256::from(200u64)];
+        let record = SellingInMarket::from_record_bytes(&legacy_bytes(&bidders, &prices)).unwrap();
+
+        assert_eq!(record.token_id, "7");
+        assert_eq!(record.offeror, Some(seller()));
+        assert_eq!(
+            record.legacy_bids,
+            vec![
+                (bidder(3), U256::from(100u64)),
+                (bidder(4), U256::from(200u64))
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_legacy_records_with_unmatched_bids() {
+        let bytes = legacy_bytes(&[bidder(3)], &[]);
+        assert!(matches!(
+            SellingInMarket::from_record_bytes(&bytes),
+            Err(Error::Bytesrepr(bytesrepr::Error::Formatting))
+        ));
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = legacy_bytes(&[], &[]);
+        bytes.push(0);
+        assert!(matches!(
+            SellingInMarket::from_record_bytes(&bytes),
+            Err(Error::TrailingBytes(1))
+        ));
+    }
+
+    #[test]
+    fn decodes_bid_books() {
+        let bid_book = vec![
+            (bidder(3), U256::from(100u64)),
+            (bidder(4), U256::from(200u64)),
+        ];
+        let bytes = bid_book.to_bytes().unwrap();
+        assert_eq!(bid_book_from_bytes(&bytes).unwrap(), bid_book);
+        assert_eq!(bid_book_from_hex(&hex::encode(&bytes)).unwrap(), bid_book);
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::BTreeMap;
+
+use casper_types::{Key, U256};
+
+use crate::{
+    constants::REFUND_BID_ON_BUY,
+    error::Error,
+    event::{EventRecord, MarketEvent},
+    listing::SellingInMarket,
+};
+
+/// A token on the market: its collection and token id.
+pub type TokenRef = (Key, String);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Listing {
+    pub offeror: Key,
+    pub minimum_offer: U256,
+    pub is_active: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sale {
+    pub sequence: u32,
+    pub timestamp: u64,
+    pub nft_contract: Key,
+    pub token_id: String,
+    pub seller: Key,
+    pub buyer: Key,
+    pub value: U256,
+    pub fee: U256,
+    pub seller_amount: U256,
+    /// One of the `TRADE_ROUTE_*` constants.
+    pub route: u8,
+}
+
+/// Market state rebuilt from one marketplace's events.
+#[derive(Clone, Debug, Default)]
+pub struct MarketState {
+    /// Every listing seen so far; sold, revoked and migrated ones stay, marked inactive.
+    pub listings: BTreeMap<TokenRef, Listing>,
+    /// Open bids per token, by bidder.
+    pub bids: BTreeMap<TokenRef, BTreeMap<Key, U256>>,
+    /// Completed trades, oldest first.
+    pub sales: Vec<Sale>,
+    /// Set once the marketplace has been shut down.
+    pub shut_down: bool,
+    next_sequence: u32,
+}
+
+impl MarketState {
+    pub fn new() -> Self {
+        MarketState::default()
+    }
+
+    /// Starts from a snapshot taken once `next_sequence` events had been emitted. Each
+    /// `selling_in_market` record comes with every bid on the listing, read from its `bid_list`
+    /// entries and their `bids` prices, empty when there is none. Bids still held in legacy
+    /// records are picked up as well.
+    pub fn from_snapshot<'a, I>(listings: I, next_sequence: u32) -> Self
+    where
+        I: IntoIterator<Item = (&'a SellingInMarket, &'a [(Key, U256)])>,
+    {
+        let mut state = MarketState {
+            next_sequence,
+            ..MarketState::default()
+        };
+        for (record, bid_book) in listings {
+            let token = (record.nft_contract, record.token_id.clone());
+            if let Some(offeror) = record.offeror {
+                state.listings.insert(
+                    token.clone(),
+                    Listing {
+                        offeror,
+                        minimum_offer: record.minimum_offer,
+                        is_active: record.is_active,
+                    },
+                );
+            }
+            for &(bidder, value) in record.legacy_bids.iter().chain(bid_book) {
+                state
+                    .bids
+                    .entry(token.clone())
+                    .or_default()
+                    .insert(bidder, value);
+            }
+        }
+        state
+    }
+
+    /// The sequence the next event must carry.
+    pub fn next_sequence(&self) -> u32 {
+        self.next_sequence
+    }
+
+    pub fn active_listings(&self) -> impl Iterator<Item = (&TokenRef, &Listing)> {
+        self.listings
+            .iter()
+            .filter(|(_, listing)| listing.is_active)
+    }
+
+    pub fn bids_on(&self, nft_contract: Key, token_id: &str) -> Option<&BTreeMap<Key, U256>> {
+        self.bids.get(&(nft_contract, token_id.to_string()))
+    }
+
+    /// Applies events in order, stopping at the first one out of sequence.
+    pub fn replay<'a, I>(&mut self, records: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = &'a EventRecord>,
+    {
+        records
+            .into_iter()
+            .try_for_each(|record| self.apply(record))
+    }
+
+    pub fn apply(&mut self, record: &EventRecord) -> Result<(), Error> {
+        if record.sequence != self.next_sequence {
+            return Err(Error::OutOfOrder {
+                expected: self.next_sequence,
+                found: record.sequence,
+            });
+        }
+        self.next_sequence += 1;
+
+        match &record.event {
+            MarketEvent::Sell {
+                nft_contract,
+                token_id,
+                offeror,
+                minimum_offer,
+                is_active,
+            }
+            | MarketEvent::Revoke {
+                nft_contract,
+                token_id,
+                offeror,
+                minimum_offer,
+                is_active,
+            } => {
+                self.listings.insert(
+                    (*nft_contract, token_id.clone()),
+                    Listing {
+                        offeror: *offeror,
+                        minimum_offer: *minimum_offer,
+                        is_active: *is_active,
+                    },
+                );
+            }
+            MarketEvent::ListingImported {
+                nft_contract,
+                token_id,
+                offeror,
+                minimum_offer,
+                is_active,
+                ..
+            } => {
+                // the imported bids follow as `Bid` events
+                self.listings.insert(
+                    (*nft_contract, token_id.clone()),
+                    Listing {
+                        offeror: *offeror,
+                        minimum_offer: *minimum_offer,
+                        is_active: *is_active,
+                    },
+                );
+            }
+            MarketEvent::ChangePrice {
+                nft_contract,
+                token_id,
+                new_price,
+                is_active,
+                ..
+            } => {
+                if let Some(listing) = self.listings.get_mut(&(*nft_contract, token_id.clone())) {
+                    listing.minimum_offer = *new_price;
+                    listing.is_active = *is_active;
+                }
+            }
+            MarketEvent::DoneSell {
+                nft_contract,
+                token_id,
+                offeror,
+                buyer,
+                value,
+                fee,
+                seller_amount,
+                route,
+            } => {
+                let token = (*nft_contract, token_id.clone());
+                self.deactivate(&token);
+                self.remove_bid(&token, buyer);
+                self.sales.push(Sale {
+                    sequence: record.sequence,
+                    timestamp: record.timestamp,
+                    nft_contract: *nft_contract,
+                    token_id: token_id.clone(),
+                    seller: *offeror,
+                    buyer: *buyer,
+                    value: *value,
+                    fee: *fee,
+                    seller_amount: *seller_amount,
+                    route: *route,
+                });
+            }
+            MarketEvent::Bid {
+                nft_contract,
+                token_id,
+                bidder,
+                value,
+                ..
+            } => {
+                // `value` is the bidder's whole bid, not the amount added
+                self.bids
+                    .entry((*nft_contract, token_id.clone()))
+                    .or_default()
+                    .insert(*bidder, *value);
+            }
+            MarketEvent::RevokeBid {
+                nft_contract,
+                token_id,
+                bidder,
+                ..
+            }
+            | MarketEvent::EmergencyBidRefunded {
+                nft_contract,
+                token_id,
+                bidder,
+                ..
+            } => {
+                self.remove_bid(&(*nft_contract, token_id.clone()), bidder);
+            }
+            MarketEvent::Refund {
+                nft_contract,
+                token_id,
+                recipient,
+                reason,
+                ..
+            } => {
+                // other refunds come with an event that already settles the bid
+                if *reason == REFUND_BID_ON_BUY {
+                    self.remove_bid(&(*nft_contract, token_id.clone()), recipient);
+                }
+            }
+            MarketEvent::ListingMigrated {
+                nft_contract,
+                token_id,
+                ..
+            } => {
+                let token = (*nft_contract, token_id.clone());
+                self.deactivate(&token);
+                self.bids.remove(&token);
+            }
+            MarketEvent::EmergencyNftReturned {
+                nft_contract,
+                token_id,
+                ..
+            } => {
+                self.deactivate(&(*nft_contract, token_id.clone()));
+            }
+            MarketEvent::EmergencyShutdown { .. } => {
+                self.shut_down = true;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn deactivate(&mut self, token: &TokenRef) {
+        if let Some(listing) = self.listings.get_mut(token) {
+            listing.is_active = false;
+        }
+    }
+
+    fn remove_bid(&mut self, token: &TokenRef, bidder: &Key) {
+        if let Some(bids) = self.bids.get_mut(token) {
+            bids.remove(bidder);
+            if bids.is_empty() {
+                self.bids.remove(token);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::account::AccountHash;
+
+    use super::*;
+    use crate::constants::{TRADE_ROUTE_BID, TRADE_ROUTE_BUY};
+
+    fn nft_contract() -> Key {
+        Key::Hash([1; 32])
+    }
+
+    fn account(byte: u8) -> Key {
+        Key::Account(AccountHash::new([byte; 32]))
+    }
+
+    fn record(sequence: u32, event: MarketEvent) -> EventRecord {
+        EventRecord {
+            sequence,
+            timestamp: 1_000 + u64::from(sequence),
+            event,
+        }
+    }
+
+    fn sell(token_id: &str, offeror: Key, minimum_offer: u64) -> MarketEvent {
+        MarketEvent::Sell {
+            nft_contract: nft_contract(),
+            token_id: token_id.to_string(),
+            offeror,
+            minimum_offer: U256::from(minimum_offer),
+            is_active: true,
+        }
+    }
+
+    fn bid(token_id: &str, offeror: Key, bidder: Key, value: u64) -> MarketEvent {
+        MarketEvent::Bid {
+            nft_contract: nft_contract(),
+            token_id: token_id.to_string(),
+            offeror,
+            bidder,
+            value: U256::from(value),
+        }
+    }
+
+    fn done_sell(token_id: &str, offeror: Key, buyer: Key, value: u64, route: u8) -> MarketEvent {
+        MarketEvent::DoneSell {
+            nft_contract: nft_contract(),
+            token_id: token_id.to_string(),
+            offeror,
+            buyer,
+            value: U256::from(value),
+            fee: U256::from(value / 10),
+            seller_amount: U256::from(value - value / 10),
+            route,
+        }
+    }
+
+    #[test]
+    fn replays_listings_bids_and_sales() {
+        let (seller, alice, bob) = (account(2), account(3), account(4));
+        let events = vec![
+            record(0, sell("1", seller, 500)),
+            record(1, sell("2", seller, 800)),
+            record(2, bid("1", seller, alice, 300)),
+            record(3, bid("1", seller, bob, 400)),
+            record(4, bid("1", seller, bob, 450)),
+            record(5, done_sell("1", seller, alice, 500, TRADE_ROUTE_BID)),
+            record(
+                6,
+                MarketEvent::ChangePrice {
+                    nft_contract: nft_contract(),
+                    token_id: "2".to_string(),
+                    offeror: seller,
+                    new_price: U256::from(700u64),
+                    is_active: true,
+                },
+            ),
+        ];
+
+        let mut state = MarketState::new();
+        state.replay(&events).unwrap();
+
+        assert_eq!(state.next_sequence(), 7);
+        let active: Vec<_> = state.active_listings().collect();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].0, &(nft_contract(), "2".to_string()));
+        assert_eq!(active[0].1.minimum_offer, U256::from(700u64));
+
+        let bids = state.bids_on(nft_contract(), "1").unwrap();
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[&bob], U256::from(450u64));
+
+        assert_eq!(state.sales.len(), 1);
+        assert_eq!(state.sales[0].buyer, alice);
+        assert_eq!(state.sales[0].timestamp, 1_005);
+        assert_eq!(state.sales[0].route, TRADE_ROUTE_BID);
+    }
+
+    #[test]
+    fn settles_bids_on_revokes_refunds_and_migrations() {
+        let (seller, alice, bob) = (account(2), account(3), account(4));
+        let events = vec![
+            record(0, sell("1", seller, 500)),
+            record(1, bid("1", seller, alice, 300)),
+            record(2, bid("1", seller, bob, 400)),
+            record(
+                3,
+                MarketEvent::RevokeBid {
+                    nft_contract: nft_contract(),
+                    token_id: "1".to_string(),
+                    offeror: seller,
+                    bidder: alice,
+                    value: U256::from(300u64),
+                    is_revoke: true,
+                },
+            ),
+            record(
+                4,
+                MarketEvent::ListingMigrated {
+                    nft_contract: nft_contract(),
+                    token_id: "1".to_string(),
+                    offeror: seller,
+                    minimum_offer: U256::from(500u64),
+                    target_marketplace: Key::Hash([7; 32]),
+                    bid_count: 1,
+                    bid_total: U256::from(400u64),
+                },
+            ),
+            record(5, MarketEvent::EmergencyShutdown { sender: seller }),
+        ];
+
+        let mut state = MarketState::new();
+        state.replay(&events).unwrap();
+
+        assert!(state.bids_on(nft_contract(), "1").is_none());
+        assert_eq!(state.active_listings().count(), 0);
+        assert!(state.shut_down);
+    }
+
+    #[test]
+    fn stops_at_events_out_of_order() {
+        let seller = account(2);
+        let events = vec![
+            record(0, sell("1", seller, 500)),
+            record(2, sell("2", seller, 500)),
+        ];
+
+        let mut state = MarketState::new();
+        assert!(matches!(
+            state.replay(&events),
+            Err(Error::OutOfOrder {
+                expected: 1,
+                found: 2
+            })
+        ));
+        assert_eq!(state.listings.len(), 1);
+    }
+
+    #[test]
+    fn starts_from_a_snapshot_with_its_bid_books() {
+        let (seller, alice, bob) = (account(2), account(3), account(4));
+        let listed = SellingInMarket {
+            token_id: "1".to_string(),
+            nft_contract: nft_contract(),
+            offeror: Some(seller),
+            minimum_offer: U256::from(500u64),
+            is_active: true,
+            legacy_bids: Vec::new(),
+        };
+        let legacy = SellingInMarket {
+            token_id: "2".to_string(),
+            nft_contract: nft_contract(),
+            offeror: None,
+            minimum_offer: U256::zero(),
+            is_active: false,
+            legacy_bids: vec![(alice, U256::from(100u64))],
+        };
+        let bid_book = vec![(alice, U256::from(300u64)), (bob, U256::from(400u64))];
+
+        let mut state = MarketState::from_snapshot(
+            vec![(&listed, bid_book.as_slice()), (&legacy, &[][..])],
+            10,
+        );
+
+        assert_eq!(state.listings.len(), 1);
+        assert_eq!(state.bids_on(nft_contract(), "1").unwrap().len(), 2);
+        assert_eq!(
+            state.bids_on(nft_contract(), "2").unwrap()[&alice],
+            U256::from(100u64)
+        );
+
+        state
+            .apply(&record(
+                10,
+                done_sell("1", seller, bob, 500, TRADE_ROUTE_BUY),
+            ))
+            .unwrap();
+        assert_eq!(state.bids_on(nft_contract(), "1").unwrap().len(), 1);
+        assert_eq!(state.active_listings().count(), 0);
+    }
+}
@@ -9,7 +9,7 @@ use types::ApiError;
 /// return those via the [`Error::User`] variant or equivalently via the [`ApiError::User`]
 /// variant.
 ///
-/// Such a user error should be in the range `[0..(u16::MAX - 30)]` (i.e. [0, 65505]) to avoid
+/// Such a user error should be in the range `[0..(u16::MAX - 34)]` (i.e. [0, 65501]) to avoid
 /// conflicting with the other `Error` variants.
 pub enum Error {
     /// ERC20 contract called from within an invalid context.
@@ -74,6 +74,14 @@ pub enum Error {
     InvalidToken,
     /// Trying to deploy a payment contract while providing an invalid deposit entry_point name.
     InvalidDepositEntryPointName,
+    /// The forwarded arguments do not decode, or set `amount` or `src_purse` themselves.
+    InvalidForwardArgs,
+    /// `nft_contract_hash` is not the hash of a contract.
+    InvalidNFTContract,
+    /// No account to register with the NFT contract: pass `token_owner`.
+    MissingTokenOwner,
+    /// The marketplace does not report the collection as CEP-78, so it cannot register owners.
+    RegisterOwnerFailed,
     /// User error.
     User(u16),
 }
@@ -110,6 +118,10 @@ const ERROR_NO_RESERVES: u16 = u16::MAX - 27; // 65508
 const ERROR_PERIOD_NOT_ELAPSED: u16 = u16::MAX - 28; // 65507
 const ERROR_INVALID_TOKEN: u16 = u16::MAX - 29; // 65506
 const ERROR_INVALID_DEPOSIT_ENTRY_POINT_NAME: u16 = u16::MAX - 30; // 65505
+const ERROR_INVALID_FORWARD_ARGS: u16 = u16::MAX - 31; // 65504
+const ERROR_INVALID_NFT_CONTRACT: u16 = u16::MAX - 32; // 65503
+const ERROR_MISSING_TOKEN_OWNER: u16 = u16::MAX - 33; // 65502
+const ERROR_REGISTER_OWNER_FAILED: u16 = u16::MAX - 34; // 65501
 
 impl From<Error> for ApiError {
     fn from(error: Error) -> Self {
@@ -145,6 +157,10 @@ impl From<Error> for ApiError {
             Error::PeriodNotElapsed => ERROR_PERIOD_NOT_ELAPSED,
             Error::InvalidToken => ERROR_INVALID_TOKEN,
             Error::InvalidDepositEntryPointName => ERROR_INVALID_DEPOSIT_ENTRY_POINT_NAME,
+            Error::InvalidForwardArgs => ERROR_INVALID_FORWARD_ARGS,
+            Error::InvalidNFTContract => ERROR_INVALID_NFT_CONTRACT,
+            Error::MissingTokenOwner => ERROR_MISSING_TOKEN_OWNER,
+            Error::RegisterOwnerFailed => ERROR_REGISTER_OWNER_FAILED,
             Error::User(user_error) => user_error,
         };
         ApiError::User(user_error)
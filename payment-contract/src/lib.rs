@@ -4,15 +4,57 @@
 extern crate alloc;
 mod converters;
 mod error;
+mod helpers;
+#[cfg(feature = "stored-session")]
+mod stored_session;
 
 use crate::converters::u512_to_u256;
 use crate::error::Error;
-use contract::contract_api::{account, runtime, system};
+use crate::helpers::get_named_arg_size;
+use contract::contract_api::{account, runtime, storage, system};
 use contract::unwrap_or_revert::UnwrapOrRevert;
-use types::{runtime_args, ContractHash, HashAddr, Key, RuntimeArgs, URef, U256, U512};
+use types::{
+    bytesrepr::{Bytes, FromBytes},
+    runtime_args, ContractHash, HashAddr, Key, RuntimeArgs, URef, U256, U512,
+};
 
+/// Named key of the caller's account holding the amount the last payment actually spent.
+const PAYMENT_SPENT_AMOUNT: &str = "payment_spent_amount";
+
+/// Standard the marketplace reports for CEP-78 collections.
+const NFT_STANDARD_CEP78: u8 = 2;
+
+/// Returned by the marketplace's `get_collection_config`: `(nft standard, paused flags)`,
+/// `(min bid, min bid increment, min bid increment bps)` and the route fee overrides.
+type CollectionConfig = ((u8, u8), (U256, U256, U256), Vec<(u8, U256)>);
+
+#[cfg(not(feature = "stored-session"))]
 #[no_mangle]
 pub extern "C" fn call() {
+    pay_deposit();
+}
+
+/// Funds a new purse with `amount` from the caller's main purse and calls
+/// `deposit_entry_point_name` on `marketplace_hash` with it.
+///
+/// When `forward_args` is passed, it holds the serialized `RuntimeArgs` of the call, to which
+/// `amount` and `src_purse` are added, so any paying entry point returning nothing can be called
+/// without a new session. Without it, the arguments of `buy`, `bid` and `increase_bid` are built
+/// from the session's own arguments.
+///
+/// CEP-78 collections tracking owners need the receiving account registered with
+/// `register_owner` first. The optional `register_owner` flag says whether to do it:
+/// - `buy`, `bid` and `increase_bid` default to registering when the marketplace reports
+///   `nft_contract_hash` as a CEP-78 collection, and to skipping it otherwise;
+/// - forwarded calls default to skipping it.
+///
+/// The account registered is `token_owner` when given, otherwise the buyer or bidder. Asking to
+/// register for a collection the marketplace does not report as CEP-78 reverts with
+/// `RegisterOwnerFailed`, before any call to the collection or the marketplace entry point.
+///
+/// Whatever the entry point leaves in the purse goes back to the main purse, and the amount spent
+/// is written under the `payment_spent_amount` named key of the account.
+pub(crate) fn pay_deposit() {
     let deposit_amount: U512 = runtime::get_named_arg("amount");
     let deposit_entry_point_name: String = runtime::get_named_arg("deposit_entry_point_name");
 
@@ -25,37 +67,96 @@ pub extern "C" fn call() {
     )
     .unwrap_or_revert_with(Error::ExcessiveAmount);
 
-    let deposit_entry_point_args = retrieve_deposit_entry_point_name_args(
-        deposit_entry_point_name.clone(),
-        new_purse,
-        u512_to_u256(deposit_amount),
-    );
-
-    if deposit_entry_point_args.is_empty() {
-        runtime::revert(Error::InvalidDepositEntryPointName);
-    }
-
-    let nft_contract_hash: Key = runtime::get_named_arg::<Key>("nft_contract_hash");
-
-    let (arg, register) = get_register_owner_args(deposit_entry_point_name.clone());
-    if register {
-        let (_, _): (String, URef) = runtime::call_contract(
-            ContractHash::new(nft_contract_hash.into_hash().unwrap_or_revert()),
-            "register_owner",
-            arg,
+    let is_forwarded = get_named_arg_size("forward_args").is_some();
+    let deposit_entry_point_args = if is_forwarded {
+        retrieve_forward_args(new_purse, u512_to_u256(deposit_amount))
+    } else {
+        let mut deposit_entry_point_args = retrieve_deposit_entry_point_name_args(
+            deposit_entry_point_name.clone(),
+            new_purse,
+            u512_to_u256(deposit_amount),
         );
-    }
+
+        if deposit_entry_point_args.is_empty() {
+            runtime::revert(Error::InvalidDepositEntryPointName);
+        }
+        add_optional_args(&mut deposit_entry_point_args);
+        deposit_entry_point_args
+    };
 
     let marketplace_input: Key = runtime::get_named_arg("marketplace_hash");
 
     let mk_contract_hash_addr: HashAddr = marketplace_input.into_hash().unwrap_or_revert();
     let mk_contract_hash: ContractHash = ContractHash::new(mk_contract_hash_addr);
 
+    let register = if get_named_arg_size("register_owner").is_some() {
+        let register = runtime::get_named_arg::<bool>("register_owner");
+        if register && !is_cep78_collection(mk_contract_hash) {
+            runtime::revert(Error::RegisterOwnerFailed);
+        }
+        register
+    } else {
+        !is_forwarded && is_cep78_collection(mk_contract_hash)
+    };
+    if register {
+        register_owner(&deposit_entry_point_name);
+    }
+
     runtime::call_contract::<()>(
         mk_contract_hash,
         &deposit_entry_point_name,
         deposit_entry_point_args,
-    )
+    );
+
+    let unspent_amount = system::get_purse_balance(new_purse).unwrap_or_revert();
+    if !unspent_amount.is_zero() {
+        system::transfer_from_purse_to_purse(
+            new_purse,
+            account::get_main_purse(),
+            unspent_amount,
+            None,
+        )
+        .unwrap_or_revert();
+    }
+    record_spent_amount(deposit_amount - unspent_amount);
+}
+
+fn record_spent_amount(spent_amount: U512) {
+    match runtime::get_key(PAYMENT_SPENT_AMOUNT) {
+        Some(key) => storage::write(key.into_uref().unwrap_or_revert(), spent_amount),
+        None => runtime::put_key(PAYMENT_SPENT_AMOUNT, storage::new_uref(spent_amount).into()),
+    }
+}
+
+// Decodes `forward_args` and adds the funded purse and its amount. Those two names are reserved so
+// the entry point cannot be handed a different purse or amount than the one funded here.
+fn retrieve_forward_args(src_purse: URef, amount: U256) -> RuntimeArgs {
+    let forward_args: Bytes = runtime::get_named_arg("forward_args");
+    let (mut args, remainder) =
+        RuntimeArgs::from_bytes(&forward_args).unwrap_or_revert_with(Error::InvalidForwardArgs);
+    if !remainder.is_empty() || args.get("amount").is_some() || args.get("src_purse").is_some() {
+        runtime::revert(Error::InvalidForwardArgs);
+    }
+    args.insert("amount", amount).unwrap_or_revert();
+    args.insert("src_purse", src_purse).unwrap_or_revert();
+    args
+}
+
+// Passes on the optional `max_price`, `deadline` and `referrer` of `buy`, `bid` and
+// `increase_bid`.
+fn add_optional_args(args: &mut RuntimeArgs) {
+    if get_named_arg_size("max_price").is_some() {
+        args.insert("max_price", runtime::get_named_arg::<U256>("max_price"))
+            .unwrap_or_revert();
+    }
+    if get_named_arg_size("deadline").is_some() {
+        args.insert("deadline", runtime::get_named_arg::<u64>("deadline"))
+            .unwrap_or_revert();
+    }
+    if get_named_arg_size("referrer").is_some() {
+        args.insert("referrer", runtime::get_named_arg::<Key>("referrer"))
+            .unwrap_or_revert();
+    }
 }
 
 fn retrieve_deposit_entry_point_name_args(
@@ -74,24 +175,42 @@ fn retrieve_deposit_entry_point_name_args(
     }
 }
 
-fn get_register_owner_args(deposit_entry_point_name: String) -> (RuntimeArgs, bool) {
-    if deposit_entry_point_name == "buy" {
-        (
-            runtime_args! {
-                "token_owner" => runtime::get_named_arg::<Key>("buyer")
-            },
-            true,
-        )
+// Only CEP-78 collections have `register_owner`. The marketplace reports the standard of the
+// collections it supports, 0 meaning an unsupported one.
+fn is_cep78_collection(marketplace_hash: ContractHash) -> bool {
+    let nft_contract_hash: Key = runtime::get_named_arg("nft_contract_hash");
+    let ((nft_standard, _), _, _): CollectionConfig = runtime::call_contract(
+        marketplace_hash,
+        "get_collection_config",
+        runtime_args! {
+            "nft_contract_hash" => nft_contract_hash
+        },
+    );
+    nft_standard == NFT_STANDARD_CEP78
+}
+
+fn register_owner(deposit_entry_point_name: &str) {
+    let token_owner: Key = if get_named_arg_size("token_owner").is_some() {
+        runtime::get_named_arg("token_owner")
+    } else if deposit_entry_point_name == "buy" {
+        runtime::get_named_arg("buyer")
     } else if deposit_entry_point_name == "bid" || deposit_entry_point_name == "increase_bid" {
-        (
-            runtime_args! {
-                "token_owner" => runtime::get_named_arg::<Key>("bidder")
-            },
-            true,
-        )
+        runtime::get_named_arg("bidder")
     } else {
-        (runtime_args! {}, false)
-    }
+        runtime::revert(Error::MissingTokenOwner)
+    };
+    let nft_contract_hash: Key = runtime::get_named_arg("nft_contract_hash");
+    let nft_contract_hash_addr: HashAddr = nft_contract_hash
+        .into_hash()
+        .unwrap_or_revert_with(Error::InvalidNFTContract);
+
+    let (_, _): (String, URef) = runtime::call_contract(
+        ContractHash::new(nft_contract_hash_addr),
+        "register_owner",
+        runtime_args! {
+            "token_owner" => token_owner
+        },
+    );
 }
 
 fn retrieve_buy_args(src_purse: URef, amount: U256) -> RuntimeArgs {
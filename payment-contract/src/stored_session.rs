@@ -0,0 +1,51 @@
+//! Stored version of the payment session.
+//!
+//! Built with the `stored-session` feature, the wasm installs a contract package whose `pay`
+//! entry point runs the payment session in the caller's context. A purchase is then a small deploy
+//! calling `pay` on the contract instead of one carrying the whole session wasm.
+//!
+//! `pay` runs with the caller's main purse, so the package is locked: no version can ever be added
+//! to it, including by the installing account. Wallets should call `pay` by the contract hash the
+//! installer stores under `payment_session_contract_hash`, pinned after reviewing that contract,
+//! and never look it up again by name. Running the installer again installs a separate package.
+use contract::contract_api::{runtime, storage};
+use types::{CLType, EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Key, Parameter};
+
+use crate::pay_deposit;
+
+const PACKAGE_HASH_KEY_NAME: &str = "payment_session_package_hash";
+const ACCESS_UREF_KEY_NAME: &str = "payment_session_access_uref";
+const CONTRACT_HASH_KEY_NAME: &str = "payment_session_contract_hash";
+const PAY_ENTRY_POINT_NAME: &str = "pay";
+
+#[no_mangle]
+pub extern "C" fn pay() {
+    pay_deposit();
+}
+
+fn entry_points() -> EntryPoints {
+    let mut entry_points = EntryPoints::new();
+    entry_points.add_entry_point(EntryPoint::new(
+        String::from(PAY_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new("amount", CLType::U512),
+            Parameter::new("deposit_entry_point_name", CLType::String),
+            Parameter::new("marketplace_hash", CLType::Key),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Session,
+    ));
+    entry_points
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let (contract_hash, _) = storage::new_locked_contract(
+        entry_points(),
+        None,
+        Some(String::from(PACKAGE_HASH_KEY_NAME)),
+        Some(String::from(ACCESS_UREF_KEY_NAME)),
+    );
+    runtime::put_key(CONTRACT_HASH_KEY_NAME, Key::from(contract_hash));
+}